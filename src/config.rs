@@ -0,0 +1,258 @@
+use pulldown_cmark::{BlockQuoteKind, HeadingLevel, Options as CmarkOptions};
+
+use crate::{
+    Category, ColumnMatcher, HeadingMatcher, HtmlMatcher, MathMode, Options, SectionSelector,
+    Selector, TableRows, UrlMode,
+};
+
+/// Everything which controls how a document is counted: the [`Options`] flags
+/// for which kinds of content to include, plus switches for content which is
+/// counted unless turned off, and any rules which need more than an on/off
+/// switch.
+///
+/// A `Config` can be made from just a set of [`Options`], with no other rules:
+///
+/// ```rust
+/// use count_md::{Config, HtmlMatcher, Options};
+///
+/// let mut config = Config::from(Options::DEFAULT | Options::IncludeBlockquotes);
+/// config.exclude_html.push(".nocount".parse::<HtmlMatcher>().unwrap());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub options: Options,
+
+    /// How inline and display math are counted, if at all.
+    pub math: MathMode,
+
+    /// How URLs are counted, if at all.
+    pub urls: UrlMode,
+
+    /// Whether the alt text of Markdown images is counted.
+    pub image_text: bool,
+
+    /// Whether the titles of links and images, like `[text](url "Title")`,
+    /// and the `alt` and `title` attributes of HTML `<img>` elements are
+    /// counted. None of these are part of the text a reader sees, so they are
+    /// not counted by default.
+    pub attributes: bool,
+
+    /// Whether the terms being defined in definition lists are counted.
+    pub definition_titles: bool,
+
+    /// Whether the definitions in definition lists are counted.
+    pub definitions: bool,
+
+    /// Whether items in ordered (numbered) lists are counted.
+    pub ordered_lists: bool,
+
+    /// Whether items in unordered (bulleted) lists are counted.
+    pub unordered_lists: bool,
+
+    /// Whether task list items which are checked, `- [x]`, are counted.
+    pub checked_tasks: bool,
+
+    /// Whether task list items which are not checked, `- [ ]`, are counted.
+    pub unchecked_tasks: bool,
+
+    /// Whether struck-through text, `~~like this~~`, is counted.
+    pub strikethrough: bool,
+
+    /// Whether superscript text, `^like this^`, is counted.
+    pub superscript: bool,
+
+    /// Whether subscript text, `~like this~`, is counted.
+    pub subscript: bool,
+
+    /// Whether the text of wikilinks like `[[Page]]` or `[[Page|label]]` is
+    /// counted: the label if there is one, otherwise the page itself.
+    pub wikilinks: bool,
+
+    /// Content which is always counted, whatever the [`Options`] (or a custom
+    /// [`CountFilter`](crate::CountFilter)) say, e.g. `heading[level=1]`. The
+    /// other rules here (including [`Config::exclude`]) still apply.
+    pub include: Vec<Selector>,
+
+    /// Content which is never counted, e.g. `blockquote > blockquote`.
+    pub exclude: Vec<Selector>,
+
+    /// Which rows of tables are counted, with [`Options::IncludeTables`].
+    pub table_rows: TableRows,
+
+    /// Table columns which are never counted, e.g. ID or type columns.
+    pub exclude_columns: Vec<ColumnMatcher>,
+
+    /// HTML elements whose contents are never counted, whether they appear in
+    /// block HTML or inline HTML, and including any Markdown inside them.
+    pub exclude_html: Vec<HtmlMatcher>,
+
+    /// The deepest list whose items are counted: with `Some(1)`, only items in
+    /// top-level lists count, and items in any list nested inside them do not.
+    pub max_list_depth: Option<usize>,
+
+    /// The deepest blockquote which is counted, with
+    /// [`Options::IncludeBlockquotes`]: with `Some(1)`, quotes count but quotes
+    /// nested inside them (e.g. earlier replies in an email) do not.
+    pub max_blockquote_depth: Option<usize>,
+
+    /// The smallest heading which is counted, with [`Options::IncludeHeadings`]:
+    /// with `Some(HeadingLevel::H2)`, only `#` and `##` headings count, and
+    /// `###` through `######` headings do not.
+    pub max_heading_level: Option<HeadingLevel>,
+
+    /// Kinds of alerts (e.g. `> [!WARNING]`) which are never counted, even
+    /// with [`Options::IncludeAlerts`].
+    pub exclude_alerts: Vec<BlockQuoteKind>,
+
+    /// Languages of fenced code blocks which are counted like prose, even
+    /// without [`Options::IncludeBlockCode`], e.g. `text` for the example prose
+    /// in a tutorial. A block's language is the first word after its opening
+    /// fence, ignoring case. The blocks are still tallied under
+    /// [`Category::BlockCode`](crate::Category::BlockCode).
+    pub code_languages: Vec<String>,
+
+    /// Whether fenced code blocks in `markdown` or `md` are counted as
+    /// Markdown, with the same rules as the rest of the document, rather than
+    /// as flat text with all its syntax. They are still code blocks, so they
+    /// only count with [`Options::IncludeBlockCode`] (or when their language is
+    /// in [`Config::code_languages`]), and their headings do not start
+    /// sections of the document.
+    pub markdown_code_blocks: bool,
+
+    /// Sections which are never counted: when a heading matches, everything
+    /// from it up to the next heading of the same or a higher level is left
+    /// out, including any subsections.
+    pub exclude_sections: Vec<HeadingMatcher>,
+
+    /// Count only the section(s) the selector matches, including their
+    /// subsections, and nothing else in the document.
+    pub section: Option<SectionSelector>,
+
+    /// The Markdown extensions the parser enables, so that the document is
+    /// counted the same way it is rendered. Start from a [`Dialect`], and then
+    /// turn individual extensions on or off:
+    ///
+    /// ```rust
+    /// use count_md::{Config, Dialect};
+    /// use pulldown_cmark::Options as CmarkOptions;
+    ///
+    /// let config = Config {
+    ///     extensions: Dialect::Gfm.extensions() | CmarkOptions::ENABLE_MATH,
+    ///     ..Config::default()
+    /// };
+    /// ```
+    pub extensions: CmarkOptions,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::from(Options::DEFAULT)
+    }
+}
+
+impl Config {
+    /// The same as the default, but leaving out struck-through text, which
+    /// editors often use to mark pending deletions.
+    pub fn editorial() -> Self {
+        Config {
+            strikethrough: false,
+            ..Config::default()
+        }
+    }
+
+    /// Whether the rules here allow content in a category to be counted, for
+    /// the categories which are controlled here rather than by the
+    /// [`Options`].
+    pub(crate) fn counts(&self, category: Category) -> bool {
+        match category {
+            Category::InlineMath | Category::DisplayMath => self.math != MathMode::Ignore,
+            Category::Urls => self.urls != UrlMode::Exclude,
+            Category::ImageText => self.image_text,
+            Category::Attributes => self.attributes,
+            Category::DefinitionTitles => self.definition_titles,
+            Category::Definitions => self.definitions,
+            Category::OrderedLists => self.ordered_lists,
+            Category::UnorderedLists => self.unordered_lists,
+            Category::CheckedTasks => self.checked_tasks,
+            Category::UncheckedTasks => self.unchecked_tasks,
+            Category::Strikethrough => self.strikethrough,
+            Category::Superscript => self.superscript,
+            Category::Subscript => self.subscript,
+            Category::Wikilinks => self.wikilinks,
+            _ => true,
+        }
+    }
+}
+
+impl From<Options> for Config {
+    fn from(options: Options) -> Self {
+        Config {
+            options,
+            math: MathMode::Ignore,
+            urls: UrlMode::Word,
+            image_text: true,
+            attributes: false,
+            definition_titles: true,
+            definitions: true,
+            ordered_lists: true,
+            unordered_lists: true,
+            checked_tasks: true,
+            unchecked_tasks: true,
+            strikethrough: true,
+            superscript: true,
+            subscript: true,
+            wikilinks: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            table_rows: TableRows::All,
+            exclude_columns: Vec::new(),
+            exclude_html: Vec::new(),
+            max_list_depth: None,
+            max_blockquote_depth: None,
+            max_heading_level: None,
+            exclude_alerts: Vec::new(),
+            code_languages: Vec::new(),
+            markdown_code_blocks: false,
+            exclude_sections: Vec::new(),
+            section: None,
+            extensions: Dialect::default().extensions(),
+        }
+    }
+}
+
+/// A flavor of Markdown, i.e. a set of extensions to CommonMark.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// Plain CommonMark, with no extensions.
+    CommonMark,
+    /// GitHub Flavored Markdown: tables, strikethrough, task lists, footnotes,
+    /// and alerts.
+    Gfm,
+    /// Every extension the parser supports.
+    #[default]
+    All,
+}
+
+impl Dialect {
+    /// The parser options for the dialect's extensions.
+    pub fn extensions(self) -> CmarkOptions {
+        match self {
+            Dialect::CommonMark => CmarkOptions::empty(),
+            Dialect::Gfm => {
+                CmarkOptions::ENABLE_TABLES
+                    | CmarkOptions::ENABLE_STRIKETHROUGH
+                    | CmarkOptions::ENABLE_TASKLISTS
+                    | CmarkOptions::ENABLE_FOOTNOTES
+                    | CmarkOptions::ENABLE_GFM
+            }
+            // Turn on everything…
+            Dialect::All => {
+                CmarkOptions::all()
+                    // …then turn off *old* footnotes…
+                    & !CmarkOptions::ENABLE_OLD_FOOTNOTES
+                    // …and finally turn back on *new* footnotes.
+                    | CmarkOptions::ENABLE_FOOTNOTES
+            }
+        }
+    }
+}
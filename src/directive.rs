@@ -0,0 +1,47 @@
+/// An instruction to `count-md` written in an HTML comment in the document
+/// itself, e.g. `<!-- count-md: off -->`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Directive {
+    /// `<!-- count-md: off -->`: stop counting until the next `on` directive,
+    /// or the end of the document.
+    Off,
+    /// `<!-- count-md: on -->`: start counting again after an `off` directive.
+    On,
+    /// `<!-- count-md: ignore-next -->`: do not count the next block, e.g. the
+    /// next paragraph, list, or table.
+    IgnoreNext,
+}
+
+impl Directive {
+    /// Parse the contents of an HTML comment as a directive, if it is one.
+    pub(crate) fn parse(comment: &str) -> Option<Directive> {
+        let instruction = comment.trim().strip_prefix("count-md:")?.trim();
+        match instruction {
+            "off" => Some(Directive::Off),
+            "on" => Some(Directive::On),
+            "ignore-next" => Some(Directive::IgnoreNext),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Directive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Directive::Off => "count-md: off",
+            Directive::On => "count-md: on",
+            Directive::IgnoreNext => "count-md: ignore-next",
+        })
+    }
+}
+
+/// How many words a [`Directive`] in a document excluded from the count.
+///
+/// Only words which would otherwise have been counted are included here: words
+/// in a blockquote after an `off` directive are excluded because of the
+/// blockquote, not the directive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirectiveTally {
+    pub directive: Directive,
+    pub excluded: u64,
+}
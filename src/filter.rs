@@ -0,0 +1,135 @@
+use pulldown_cmark::{Event, Tag};
+
+use crate::{report::Categories, Category, Options};
+
+/// Decides which text in a document counts.
+///
+/// [`Options`] is the built-in filter: it includes or excludes text based on
+/// the [`Category`]s it is in. To encode other rules, implement this trait and
+/// pass your filter to [`report_with_filter`](crate::report_with_filter) or
+/// [`count_with_filter`](crate::count_with_filter).
+///
+/// A filter replaces the inclusion rules from [`Options`], so to keep those as
+/// well, check them from your own filter. For example, to skip list items which
+/// start with "TODO":
+///
+/// ```rust
+/// use count_md::{count_with_filter, Config, Context, CountFilter, Options};
+/// use pulldown_cmark::{Event, Tag, TagEnd};
+///
+/// struct SkipTodos {
+///     options: Options,
+///     /// Whether we are at the start of a list item.
+///     item_start: bool,
+///     /// Whether the current list item is a TODO.
+///     in_todo: bool,
+/// }
+///
+/// impl CountFilter for SkipTodos {
+///     fn observe(&mut self, event: &Event<'_>) {
+///         match event {
+///             Event::Start(Tag::Item) => self.item_start = true,
+///             Event::End(TagEnd::Item) => self.in_todo = false,
+///             _ => {}
+///         }
+///     }
+///
+///     fn includes(&mut self, context: &Context<'_, '_>, text: &str) -> bool {
+///         if std::mem::take(&mut self.item_start) {
+///             self.in_todo = text.starts_with("TODO");
+///         }
+///
+///         !self.in_todo && self.options.includes(context, text)
+///     }
+/// }
+///
+/// let filter = SkipTodos {
+///     options: Options::DEFAULT,
+///     item_start: false,
+///     in_todo: false,
+/// };
+///
+/// let text = "Some text.\n\n- TODO: write more\n- A real item";
+/// assert_eq!(count_with_filter(text, &Config::default(), filter), 5);
+/// ```
+pub trait CountFilter {
+    /// Called with every event from the parser, before it is counted. Filters
+    /// which need to know about more than the currently-open tags (e.g. "the
+    /// first paragraph after a heading") can keep track of it here.
+    fn observe(&mut self, _event: &Event<'_>) {}
+
+    /// Whether `text`, at the point in the document described by the
+    /// `context`, should be counted.
+    ///
+    /// This is called for each piece of text the parser reports, not for each
+    /// word: a single word may be split across several pieces of text (e.g.
+    /// `un*believ*able`), and a single piece of text may have many words.
+    fn includes(&mut self, context: &Context<'_, '_>, text: &str) -> bool;
+}
+
+impl CountFilter for Options {
+    fn includes(&mut self, context: &Context<'_, '_>, _text: &str) -> bool {
+        context.categories.allowed_by(self)
+    }
+}
+
+impl<F: CountFilter + ?Sized> CountFilter for &mut F {
+    fn observe(&mut self, event: &Event<'_>) {
+        (**self).observe(event);
+    }
+
+    fn includes(&mut self, context: &Context<'_, '_>, text: &str) -> bool {
+        (**self).includes(context, text)
+    }
+}
+
+/// Where some text appears in a document.
+pub struct Context<'s, 'a> {
+    pub(crate) tags: &'s [Tag<'a>],
+    pub(crate) kind: TextKind,
+    pub(crate) categories: Categories,
+}
+
+impl<'s, 'a> Context<'s, 'a> {
+    /// Every Markdown tag which is open around the text, from outermost to
+    /// innermost.
+    pub fn tags(&self) -> &'s [Tag<'a>] {
+        self.tags
+    }
+
+    /// What kind of text this is: ordinary text, inline code, math, a URL, or
+    /// a title or `alt` attribute.
+    pub fn kind(&self) -> TextKind {
+        self.kind
+    }
+
+    /// Whether any of the open tags matches the predicate, i.e. whether the
+    /// text is anywhere inside some kind of content, however deeply nested.
+    pub fn inside(&self, predicate: impl Fn(&Tag<'a>) -> bool) -> bool {
+        self.tags.iter().any(predicate)
+    }
+
+    /// Whether the text is in the given category.
+    pub fn is_in(&self, category: Category) -> bool {
+        self.categories.contains(category)
+    }
+
+    /// Every category the text is in.
+    pub fn categories(&self) -> impl Iterator<Item = Category> {
+        self.categories.iter()
+    }
+}
+
+/// The kinds of text the parser reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextKind {
+    Text,
+    InlineCode,
+    InlineMath,
+    DisplayMath,
+    /// An autolink like `<https://example.com>`, or a bare URL in the text.
+    Url,
+    /// The title of a link or image, or the `alt` or `title` attribute of an
+    /// HTML `<img>` element.
+    Attribute,
+}
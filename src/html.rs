@@ -0,0 +1,567 @@
+//! Just enough HTML handling to count the prose in it.
+//!
+//! The HTML in Markdown documents is rarely well-formed XML: it has void
+//! elements like `<br>`, unquoted attributes, named entities like `&nbsp;`, and
+//! elements which are opened in one HTML block and closed in another, with
+//! Markdown in between. So rather than a strict parser, this is a lenient
+//! tokenizer in the spirit of the HTML5 one, plus a stack of open elements.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use pulldown_cmark::{Event, Tag, TagEnd};
+
+/// A piece of HTML.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    StartTag {
+        /// The tag name, lowercased.
+        name: String,
+        /// Each attribute's (lowercased) name and (decoded) value.
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    EndTag {
+        /// The tag name, lowercased.
+        name: String,
+    },
+    /// Text, with entities already decoded.
+    Text(Cow<'a, str>),
+    /// The contents of a comment, i.e. without the `<!--` and `-->`.
+    Comment(&'a str),
+}
+
+/// Splits HTML into [`Token`]s. Doctypes and processing instructions are
+/// skipped; malformed markup is treated as text rather than as an error.
+pub(crate) struct Tokenizer<'a> {
+    rest: &'a str,
+    /// The name of the raw text element (e.g. `script`) we are inside of, if
+    /// any. Its contents are a single text token, even if they contain `<`.
+    raw_text: Option<String>,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(html: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            rest: html,
+            raw_text: None,
+        }
+    }
+
+    fn text(&mut self, len: usize) -> Token<'a> {
+        let (text, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Token::Text(html_escape::decode_html_entities(text))
+    }
+
+    /// Skip past the next occurrence of `end`, or to the end of the input.
+    fn skip_past(&mut self, end: &str) {
+        self.rest = match self.rest.find(end) {
+            Some(index) => &self.rest[index + end.len()..],
+            None => "",
+        };
+    }
+
+    fn tag(&mut self, is_end: bool) -> Token<'a> {
+        let start = if is_end { 2 } else { 1 };
+        let after_lt = &self.rest[start..];
+        let name_len = after_lt
+            .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            .unwrap_or(after_lt.len());
+        let name = after_lt[..name_len].to_ascii_lowercase();
+
+        // Attributes may contain `>` inside quotes, so find the end of the tag
+        // by walking over them rather than just searching for `>`.
+        let mut attributes = Vec::new();
+        let mut rest = &after_lt[name_len..];
+        let mut self_closing = false;
+        loop {
+            rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+            if let Some(after) = rest.strip_prefix("/>") {
+                self_closing = true;
+                rest = after;
+                break;
+            }
+
+            match rest.chars().next() {
+                None => break,
+                Some('>') => {
+                    rest = &rest[1..];
+                    break;
+                }
+                Some('/') => {
+                    rest = &rest[1..];
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            let name_len = rest
+                .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+                .unwrap_or(rest.len())
+                // An attribute name can start with `=`, which would otherwise
+                // give us an empty name and loop forever.
+                .max(1);
+            let attribute = rest[..name_len].to_ascii_lowercase();
+            rest = rest[name_len..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+
+            let mut value = "";
+            if let Some(after_eq) = rest.strip_prefix('=') {
+                let after_eq = after_eq.trim_start_matches(|c: char| c.is_ascii_whitespace());
+                match after_eq.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let quoted = &after_eq[1..];
+                        let len = quoted.find(quote).unwrap_or(quoted.len());
+                        value = &quoted[..len];
+                        rest = quoted.get(len + 1..).unwrap_or("");
+                    }
+                    _ => {
+                        let len = after_eq
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(after_eq.len());
+                        value = &after_eq[..len];
+                        rest = &after_eq[len..];
+                    }
+                }
+            }
+
+            let value = html_escape::decode_html_entities(value).into_owned();
+            attributes.push((attribute, value));
+        }
+
+        self.rest = rest;
+
+        if is_end {
+            Token::EndTag { name }
+        } else {
+            if RAW_TEXT_ELEMENTS.contains(&name.as_str()) && !self_closing {
+                self.raw_text = Some(name.clone());
+            }
+            Token::StartTag {
+                name,
+                attributes,
+                self_closing,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(name) = self.raw_text.take() {
+            let close = format!("</{name}");
+            let len = find_ignore_ascii_case(self.rest, &close).unwrap_or(self.rest.len());
+            if len > 0 {
+                let (text, rest) = self.rest.split_at(len);
+                self.rest = rest;
+                return Some(Token::Text(Cow::Borrowed(text)));
+            }
+        }
+
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let Some(lt) = self.rest.find('<') else {
+                return Some(self.text(self.rest.len()));
+            };
+
+            if lt > 0 {
+                return Some(self.text(lt));
+            }
+
+            let after_lt = &self.rest[1..];
+            if let Some(comment) = after_lt.strip_prefix("!--") {
+                let len = comment.find("-->").unwrap_or(comment.len());
+                self.rest = comment.get(len + 3..).unwrap_or("");
+                return Some(Token::Comment(&comment[..len]));
+            } else if after_lt.starts_with(['!', '?']) {
+                self.skip_past(">");
+            } else if after_lt.starts_with('/')
+                && after_lt[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+            {
+                return Some(self.tag(true));
+            } else if after_lt.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                return Some(self.tag(false));
+            } else {
+                // A bare `<`, as in `a < b`, is just text.
+                let len = after_lt
+                    .find('<')
+                    .map_or(self.rest.len(), |index| index + 1);
+                return Some(self.text(len));
+            }
+        }
+    }
+}
+
+fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Elements whose contents are not parsed as HTML.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Elements whose contents are never prose, and so are never counted.
+const NON_PROSE_ELEMENTS: &[&str] = &["script", "style", "template", "noscript"];
+
+/// Elements which never have contents or an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose end tag is optional. These are treated as closed at the end
+/// of the HTML block which opened them, so that e.g. an unclosed `<p>` does not
+/// swallow the rest of the document.
+const OPTIONAL_END_ELEMENTS: &[&str] = &[
+    "p", "li", "dt", "dd", "tr", "td", "th", "thead", "tbody", "tfoot", "option", "optgroup", "rt",
+    "rp", "colgroup", "caption",
+];
+
+/// Elements which can appear in the middle of a word, like `un<em>believ</em>able`.
+/// Every other element separates the words on either side of it.
+const PHRASING_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "del", "dfn", "em", "i", "ins", "kbd",
+    "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
+pub(crate) fn is_phrasing(name: &str) -> bool {
+    PHRASING_ELEMENTS.contains(&name)
+}
+
+/// A rule for HTML elements whose contents should never be counted, written
+/// like a (very) simple CSS selector:
+///
+/// - `aside` matches elements by tag name.
+/// - `.nocount` matches elements by class.
+/// - `[data-nocount]` matches elements with an attribute, whatever its value.
+/// - `[data-count=no]` matches elements with an attribute with a given value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HtmlMatcher {
+    Tag(String),
+    Class(String),
+    Attribute { name: String, value: Option<String> },
+}
+
+impl HtmlMatcher {
+    pub(crate) fn matches(&self, name: &str, attributes: &[(String, String)]) -> bool {
+        match self {
+            HtmlMatcher::Tag(tag) => tag == name,
+            HtmlMatcher::Class(class) => attributes
+                .iter()
+                .filter(|(attribute, _)| attribute == "class")
+                .any(|(_, classes)| classes.split_ascii_whitespace().any(|c| c == class)),
+            HtmlMatcher::Attribute {
+                name: attribute_name,
+                value,
+            } => attributes.iter().any(|(attribute, actual)| {
+                attribute == attribute_name && value.as_ref().is_none_or(|value| value == actual)
+            }),
+        }
+    }
+}
+
+impl std::str::FromStr for HtmlMatcher {
+    type Err = ParseHtmlMatcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let error = || ParseHtmlMatcherError(s.to_string());
+
+        if let Some(class) = s.strip_prefix('.') {
+            if class.is_empty() || class.contains(char::is_whitespace) {
+                return Err(error());
+            }
+            return Ok(HtmlMatcher::Class(class.to_string()));
+        }
+
+        if let Some(attribute) = s.strip_prefix('[') {
+            let attribute = attribute.strip_suffix(']').ok_or_else(error)?;
+            let (name, value) = match attribute.split_once('=') {
+                Some((name, value)) => {
+                    let value = value.trim();
+                    let value = value
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                        .unwrap_or(value);
+                    (name, Some(value.to_string()))
+                }
+                None => (attribute, None),
+            };
+
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(error());
+            }
+            return Ok(HtmlMatcher::Attribute {
+                name: name.to_ascii_lowercase(),
+                value,
+            });
+        }
+
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(error());
+        }
+        Ok(HtmlMatcher::Tag(s.to_ascii_lowercase()))
+    }
+}
+
+/// The error when a string is not a valid [`HtmlMatcher`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseHtmlMatcherError(String);
+
+impl std::fmt::Display for ParseHtmlMatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid HTML matcher '{}': expected a tag name like `aside`, a class like \
+            `.nocount`, or an attribute like `[data-nocount]` or `[data-count=no]`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseHtmlMatcherError {}
+
+/// Which of the elements each HTML block leaves open are closed by later HTML.
+/// Those elements contain the Markdown after the block, as in:
+///
+/// ```markdown
+/// <div class="note">
+///
+/// Some *Markdown*.
+///
+/// </div>
+/// ```
+///
+/// Otherwise, a single unclosed `<div>` would swallow the rest of the document.
+/// So the whole document is read ahead once, tracking the open elements the
+/// same way [`Elements`] does, to find out.
+pub(crate) fn closed_later(events: &[Event<'_>]) -> VecDeque<Vec<bool>> {
+    let mut lookahead = Lookahead::default();
+    let mut block = String::new();
+    let mut in_block = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::HtmlBlock) => in_block = true,
+            Event::Html(html) if in_block => block.push_str(html),
+            Event::Html(html) => lookahead.html(html, true),
+            Event::InlineHtml(html) => lookahead.html(html, false),
+            Event::End(tag) if !crate::is_inline_end(tag) => {
+                lookahead.end_inline();
+                if *tag == TagEnd::HtmlBlock {
+                    in_block = false;
+                    lookahead.html(&std::mem::take(&mut block), true);
+                    lookahead.end_block();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    lookahead.closed.into()
+}
+
+/// The open elements while reading ahead for [`closed_later`].
+#[derive(Debug, Default)]
+struct Lookahead {
+    open: Vec<Opened>,
+    /// For each HTML block, whether each of the elements it left open has been
+    /// closed since.
+    closed: Vec<Vec<bool>>,
+}
+
+#[derive(Debug)]
+struct Opened {
+    name: String,
+    from_block: bool,
+    /// If the element's HTML block left it open, which of the blocks in
+    /// [`Lookahead::closed`] that was, and which of the elements it left open.
+    left_open: Option<(usize, usize)>,
+}
+
+impl Lookahead {
+    fn html(&mut self, html: &str, from_block: bool) {
+        for token in Tokenizer::new(html) {
+            match token {
+                Token::StartTag {
+                    name, self_closing, ..
+                } => {
+                    if !self_closing && !VOID_ELEMENTS.contains(&name.as_str()) {
+                        self.open.push(Opened {
+                            name,
+                            from_block,
+                            left_open: None,
+                        });
+                    }
+                }
+                Token::EndTag { name } => {
+                    if let Some(index) = self.open.iter().rposition(|opened| opened.name == name) {
+                        self.close(index);
+                    }
+                }
+                Token::Text(_) | Token::Comment(_) => {}
+            }
+        }
+    }
+
+    /// Close the element at `index` in the stack, and everything inside it.
+    fn close(&mut self, index: usize) {
+        for opened in self.open.drain(index..) {
+            if let Some((block, element)) = opened.left_open {
+                self.closed[block][element] = true;
+            }
+        }
+    }
+
+    fn end_block(&mut self) {
+        while self
+            .open
+            .last()
+            .is_some_and(|opened| OPTIONAL_END_ELEMENTS.contains(&opened.name.as_str()))
+        {
+            self.close(self.open.len() - 1);
+        }
+
+        // Everything this block opened is at the top of the stack, above the
+        // elements earlier blocks left open.
+        let new = self
+            .open
+            .iter()
+            .rev()
+            .take_while(|opened| opened.left_open.is_none())
+            .count();
+        let start = self.open.len() - new;
+        let block = self.closed.len();
+        for (element, opened) in self.open[start..].iter_mut().enumerate() {
+            opened.left_open = Some((block, element));
+        }
+        self.closed.push(vec![false; new]);
+    }
+
+    fn end_inline(&mut self) {
+        while self.open.last().is_some_and(|opened| !opened.from_block) {
+            self.open.pop();
+        }
+    }
+}
+
+/// The HTML elements which are currently open, across all of the HTML blocks
+/// and inline HTML in the document.
+#[derive(Debug, Default)]
+pub(crate) struct Elements {
+    open: Vec<Element>,
+    /// For each HTML block still to come, whether each of the elements it
+    /// leaves open is closed by later HTML: see [`closed_later`].
+    closed_later: VecDeque<Vec<bool>>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    /// Whether the element was opened in an HTML block (as opposed to inline
+    /// HTML in a paragraph).
+    from_block: bool,
+    /// Whether the element matched one of the rules for excluding HTML.
+    excluded: bool,
+    /// For an element left open at the end of its HTML block, whether later
+    /// HTML is known to close it.
+    settled: bool,
+}
+
+impl Elements {
+    pub(crate) fn start(
+        &mut self,
+        name: String,
+        self_closing: bool,
+        from_block: bool,
+        excluded: bool,
+    ) {
+        if self_closing || VOID_ELEMENTS.contains(&name.as_str()) {
+            return;
+        }
+
+        self.open.push(Element {
+            name,
+            from_block,
+            excluded,
+            settled: false,
+        });
+    }
+
+    /// Close the innermost open element with this name, and everything inside
+    /// it. An end tag with no matching open element is ignored.
+    pub(crate) fn end(&mut self, name: &str) {
+        if let Some(index) = self.open.iter().rposition(|element| element.name == name) {
+            self.open.truncate(index);
+        }
+    }
+
+    /// Use the answers from [`closed_later`] for the HTML blocks to come,
+    /// returning the ones in use until now.
+    pub(crate) fn read_ahead(&mut self, closed_later: VecDeque<Vec<bool>>) -> VecDeque<Vec<bool>> {
+        std::mem::replace(&mut self.closed_later, closed_later)
+    }
+
+    /// Close the elements whose end tags are optional, at the end of the HTML
+    /// block which opened them. Of the other elements the block left open,
+    /// only keep the ones which later HTML closes: the rest only contain the
+    /// block itself.
+    pub(crate) fn end_block(&mut self) {
+        while self
+            .open
+            .last()
+            .is_some_and(|element| OPTIONAL_END_ELEMENTS.contains(&element.name.as_str()))
+        {
+            self.open.pop();
+        }
+
+        let mut closed_later = self
+            .closed_later
+            .pop_front()
+            .unwrap_or_default()
+            .into_iter();
+        self.open.retain_mut(|element| {
+            if !element.from_block || element.settled {
+                return true;
+            }
+            element.settled = closed_later.next().unwrap_or(false);
+            element.settled
+        });
+    }
+
+    /// Close any elements opened by inline HTML, at the end of the Markdown
+    /// block which contained them.
+    pub(crate) fn end_inline(&mut self) {
+        while self.open.last().is_some_and(|element| !element.from_block) {
+            self.open.pop();
+        }
+    }
+
+    /// Whether we are inside an element opened by an HTML block, and so
+    /// Markdown content counts as block HTML.
+    pub(crate) fn in_block(&self) -> bool {
+        self.open.iter().any(|element| element.from_block)
+    }
+
+    /// Whether we are inside an element matched by an [`HtmlMatcher`].
+    pub(crate) fn in_excluded(&self) -> bool {
+        self.open.iter().any(|element| element.excluded)
+    }
+
+    /// Whether we are inside an element like `<script>`, whose contents are
+    /// not prose at all.
+    pub(crate) fn in_non_prose(&self) -> bool {
+        self.open
+            .iter()
+            .any(|element| NON_PROSE_ELEMENTS.contains(&element.name.as_str()))
+    }
+}
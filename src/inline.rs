@@ -0,0 +1,151 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    report::{Attribution, CountReport},
+    url, UrlMode,
+};
+
+/// Inline text which has not yet been split into words.
+///
+/// The parser reports a single word in multiple pieces whenever inline markup
+/// interrupts it: `un**believ**able` is three `Text` events, as are `foo\_bar`
+/// and (with smart punctuation) `don't`. Splitting each piece on its own would
+/// count each of those as multiple words, so instead the pieces are collected
+/// here until a block boundary, and only then split into words.
+///
+/// The same goes for bare URLs: the parser splits `Rust_(language)`,
+/// `~user`, and `&amp;` out of a URL just as it does out of a word, so URLs
+/// are only picked out of the text once it is all joined up again.
+#[derive(Debug)]
+pub(crate) struct InlineText {
+    text: String,
+    runs: Vec<Run>,
+    urls: UrlMode,
+}
+
+/// A piece of [`InlineText`] which is all counted the same way.
+#[derive(Debug)]
+struct Run {
+    /// The byte offset in the text where the run starts.
+    start: usize,
+    attribution: Attribution,
+    /// How a URL starting in the run is counted, if the run is prose which
+    /// has the start of a URL in it.
+    url: Option<Attribution>,
+}
+
+impl InlineText {
+    pub(crate) fn new(urls: UrlMode) -> InlineText {
+        InlineText {
+            text: String::new(),
+            runs: Vec::new(),
+            urls,
+        }
+    }
+
+    pub(crate) fn push(&mut self, text: &str, attribution: Attribution) {
+        self.push_run(text, attribution, None);
+    }
+
+    /// Add some prose with the start of a URL in it, which is counted as the
+    /// `url` attribution says, however much of the text after it the URL
+    /// turns out to take up.
+    pub(crate) fn push_with_url(&mut self, text: &str, attribution: Attribution, url: Attribution) {
+        self.push_run(text, attribution, Some(url));
+    }
+
+    fn push_run(&mut self, text: &str, attribution: Attribution, url: Option<Attribution>) {
+        if text.is_empty() {
+            return;
+        }
+
+        let continues_last = self
+            .runs
+            .last()
+            .is_some_and(|run| run.attribution == attribution && run.url == url);
+
+        if !continues_last {
+            self.runs.push(Run {
+                start: self.text.len(),
+                attribution,
+                url,
+            });
+        }
+
+        self.text.push_str(text);
+    }
+
+    /// Separate whatever comes next from the text so far, e.g. for a hard
+    /// break or a footnote reference.
+    pub(crate) fn boundary(&mut self) {
+        if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+    }
+
+    /// Handle a soft break (a newline within a paragraph). Normally that is
+    /// just a word boundary, but a word hyphenated across the line break is
+    /// rejoined into a single word.
+    pub(crate) fn soft_break(&mut self) {
+        let mut chars = self.text.chars().rev();
+        let hyphenated =
+            chars.next() == Some('-') && chars.next().is_some_and(|c| c.is_alphabetic());
+
+        if hyphenated {
+            self.text.pop();
+        } else {
+            self.boundary();
+        }
+    }
+
+    /// Split all of the text so far into words and record them in the report,
+    /// leaving the buffer empty. Each word is counted according to the run in
+    /// which it starts, except for the words in a URL, which are counted
+    /// according to the run in which the URL starts.
+    pub(crate) fn flush(&mut self, report: &mut CountReport) {
+        let urls = url::find(&self.text)
+            .into_iter()
+            .filter_map(|range| Some((self.runs[self.run_at(range.start)].url?, range)))
+            .collect::<Vec<_>>();
+
+        let mut counts = vec![0; self.runs.len()];
+        let mut url_counts = vec![0; urls.len()];
+        let mut next_url = 0;
+        for (start, _) in self.text.unicode_word_indices() {
+            while urls
+                .get(next_url)
+                .is_some_and(|(_, range)| range.end <= start)
+            {
+                next_url += 1;
+            }
+
+            match urls.get(next_url) {
+                Some((_, range)) if range.contains(&start) => url_counts[next_url] += 1,
+                _ => counts[self.run_at(start)] += 1,
+            }
+        }
+
+        for (run, count) in self.runs.iter().zip(counts) {
+            report.record(run.attribution, count);
+        }
+
+        for ((attribution, _), count) in urls.into_iter().zip(url_counts) {
+            let words = if self.urls == UrlMode::Segments {
+                count
+            } else {
+                1
+            };
+            report.record(attribution, words);
+        }
+
+        self.text.clear();
+        self.runs.clear();
+    }
+
+    /// The index of the run which the byte at `index` is in.
+    fn run_at(&self, index: usize) -> usize {
+        self.runs
+            .partition_point(|run| run.start <= index)
+            .saturating_sub(1)
+    }
+}
@@ -1,9 +1,38 @@
 #![doc = include_str!("../README.md")]
 
 use bitflags::bitflags;
-use pulldown_cmark::{Event, Options as CmarkOptions, Parser, Tag, TagEnd};
-use unicode_segmentation::UnicodeSegmentation;
-use xmlparser::{Token, Tokenizer};
+use pulldown_cmark::{
+    BlockQuoteKind, CodeBlockKind, Event, HeadingLevel, LinkType, Parser, Tag, TagEnd,
+};
+
+mod config;
+mod directive;
+mod filter;
+mod html;
+mod inline;
+mod math;
+mod outline;
+mod report;
+mod section;
+mod selector;
+mod table;
+mod url;
+
+pub use config::{Config, Dialect};
+pub use directive::{Directive, DirectiveTally};
+pub use filter::{Context, CountFilter, TextKind};
+pub use html::{HtmlMatcher, ParseHtmlMatcherError};
+pub use math::MathMode;
+pub use outline::Section;
+pub use report::{Category, CountReport, Rule, Tally};
+pub use section::{HeadingMatcher, ParseHeadingMatcherError, SectionSelector, TextMatcher};
+pub use selector::{ParseSelectorError, Selector};
+pub use table::{ColumnMatcher, ParseColumnMatcherError, TableRows};
+pub use url::UrlMode;
+
+use html::{Elements, Token, Tokenizer};
+use inline::InlineText;
+use report::{Attribution, Categories, Rules};
 
 /// Count some Markdown, using the default [`Options`].
 pub fn count(text: &str) -> u64 {
@@ -12,120 +41,887 @@ pub fn count(text: &str) -> u64 {
 
 /// Count some Markdown, using the supplied [`Options`].
 pub fn count_with_options(text: &str, options: Options) -> u64 {
-    // Turn on everything…
-    let cmark_options = CmarkOptions::all()
-        // …then turn off *old* footnotes…
-        & !CmarkOptions::ENABLE_OLD_FOOTNOTES
-        // …and finally turn back on *new* footnotes.
-        | CmarkOptions::ENABLE_FOOTNOTES;
+    count_with_config(text, &Config::from(options))
+}
 
-    let mut parser = Parser::new_ext(text, cmark_options);
+pub fn count_with_options_and_parser(options: Options, parser: &mut Parser) -> u64 {
+    report_with_options_and_parser(options, parser).total()
+}
 
-    count_with_options_and_parser(options, parser.by_ref())
+/// Count some Markdown, using the supplied [`Config`].
+pub fn count_with_config(text: &str, config: &Config) -> u64 {
+    report_with_config(text, config).total()
 }
 
-pub fn count_with_options_and_parser(options: Options, parser: &mut Parser) -> u64 {
-    let mut state = State {
-        in_code_block: false,
-        blockquote_level: 0,
-        in_metadata_block: false,
-        in_footnote: false,
-        in_table: false,
-        in_heading: false,
+/// Count some Markdown using the default [`Options`], breaking the result down
+/// by [`Category`].
+pub fn report(text: &str) -> CountReport {
+    report_with_options(text, Options::DEFAULT)
+}
+
+/// Count some Markdown using the supplied [`Options`], breaking the result down
+/// by [`Category`].
+pub fn report_with_options(text: &str, options: Options) -> CountReport {
+    report_with_config(text, &Config::from(options))
+}
+
+/// Count some Markdown using the supplied [`Config`], breaking the result down
+/// by [`Category`].
+pub fn report_with_config(text: &str, config: &Config) -> CountReport {
+    report_with_config_and_parser(config, parser(text, config).by_ref())
+}
+
+fn parser<'t>(text: &'t str, config: &Config) -> Parser<'t> {
+    Parser::new_ext(text, config.extensions)
+}
+
+pub fn report_with_options_and_parser(options: Options, parser: &mut Parser) -> CountReport {
+    report_with_config_and_parser(&Config::from(options), parser)
+}
+
+pub fn report_with_config_and_parser(config: &Config, parser: &mut Parser) -> CountReport {
+    report_with_filter_and_parser(config, config.options, parser)
+}
+
+/// Count some Markdown, using a custom [`CountFilter`] to decide which text
+/// counts instead of the [`Options`] in the [`Config`].
+pub fn count_with_filter(text: &str, config: &Config, filter: impl CountFilter) -> u64 {
+    report_with_filter(text, config, filter).total()
+}
+
+/// Count some Markdown, using a custom [`CountFilter`] to decide which text
+/// counts instead of the [`Options`] in the [`Config`], breaking the result
+/// down by [`Category`].
+pub fn report_with_filter(text: &str, config: &Config, filter: impl CountFilter) -> CountReport {
+    report_with_filter_and_parser(config, filter, parser(text, config).by_ref())
+}
+
+pub fn report_with_filter_and_parser(
+    config: &Config,
+    filter: impl CountFilter,
+    parser: &mut Parser,
+) -> CountReport {
+    let mut counter = Counter {
+        config,
+        filter,
+        state: State::new(),
+        inline: InlineText::new(config.urls),
+        report: CountReport::default(),
+        pending: None,
     };
 
-    // TODO: check whether items other than blockquotes can be nested!
-    let mut count = 0;
-    for event in parser {
+    let events = parser.collect::<Vec<_>>();
+    counter.state.html.read_ahead(html::closed_later(&events));
+    for event in events {
+        counter.header_event(event);
+    }
+
+    counter.finish()
+}
+
+/// Everything needed to count a single document.
+struct Counter<'c, 'a, F> {
+    config: &'c Config,
+    filter: F,
+    state: State<'a>,
+    inline: InlineText,
+    report: CountReport,
+    /// The events in the heading or table header row currently being read, if
+    /// any.
+    pending: Option<Vec<Event<'a>>>,
+}
+
+impl<'a, F: CountFilter> Counter<'_, 'a, F> {
+    /// Whether a section is counted at all depends on the text of its heading,
+    /// and whether a table column is counted may depend on the text of its
+    /// header cell. So hold on to the events in each heading and table header
+    /// row until the end of it, and only then count them. Only top-level
+    /// headings start sections: not those in blockquotes, lists, footnotes, or
+    /// Markdown code blocks.
+    fn header_event(&mut self, event: Event<'a>) {
+        match (&mut self.pending, event) {
+            (None, event @ Event::Start(Tag::Heading { .. } | Tag::TableHead)) => {
+                self.pending = Some(vec![event]);
+            }
+            (Some(_), event @ Event::End(TagEnd::Heading(_) | TagEnd::TableHead)) => {
+                let events = self.pending.take().unwrap_or_default();
+                match event {
+                    Event::End(TagEnd::Heading(level)) if self.state.stack.is_empty() => {
+                        self.start_section(level, &events)
+                    }
+                    Event::End(TagEnd::Heading(_)) => {}
+                    _ => {
+                        self.state.excluded_columns =
+                            table::excluded_columns(&self.config.exclude_columns, &events);
+                    }
+                }
+
+                for event in events {
+                    self.count(event);
+                }
+                self.count(event);
+            }
+            (Some(events), event) => events.push(event),
+            (None, event) => self.count(event),
+        }
+    }
+
+    fn count(&mut self, event: Event<'a>) {
+        self.filter.observe(&event);
+
         use Event::*;
         match event {
             Text(text) => {
-                if state.allowed_for(&options) {
-                    count += text.unicode_words().count() as u64;
+                if let Some(markdown) = &mut self.state.markdown_block {
+                    markdown.push_str(&text);
+                    return;
+                }
+
+                if self.state.in_top_level_heading() {
+                    self.report.extend_section_title(&text);
+                }
+
+                if self.state.in_autolink() {
+                    self.url(&text);
+                } else if !self.state.html.in_non_prose() {
+                    self.text(&text);
                 }
             }
 
             Code(text) => {
-                if options.contains(Options::IncludeInlineCode) {
-                    count += text.unicode_words().count() as u64;
+                if self.state.in_top_level_heading() {
+                    self.report.extend_section_title(&text);
                 }
+
+                let attribution = self.attribution(TextKind::InlineCode, &text);
+                self.inline.push(&text, attribution);
             }
 
-            Start(tag) => match tag {
-                Tag::CodeBlock(_) => state.in_code_block = true,
-                Tag::BlockQuote(_) => state.blockquote_level += 1,
-                Tag::MetadataBlock(_) => state.in_metadata_block = true,
-                Tag::FootnoteDefinition(_) => state.in_footnote = true,
-                Tag::Table(_) => state.in_table = true,
-                Tag::Heading { .. } => state.in_heading = true,
-                _ => {}
-            },
-
-            End(tag) => match tag {
-                TagEnd::CodeBlock => state.in_code_block = false,
-                TagEnd::BlockQuote(_) => state.blockquote_level -= 1,
-                TagEnd::MetadataBlock(_) => state.in_metadata_block = false,
-                TagEnd::FootnoteDefinition => state.in_footnote = false,
-                TagEnd::Table => state.in_table = false,
-                TagEnd::Heading(_) => state.in_heading = false,
-                _ => {}
-            },
+            Start(tag) => {
+                if !is_inline(&tag) {
+                    self.inline.flush(&mut self.report);
+                }
+
+                // Like sections, the outline only has top-level headings.
+                if let Tag::Heading { level, .. } = tag {
+                    if self.state.stack.is_empty() {
+                        self.report.open_section(level);
+                    }
+                }
+
+                if let Tag::Link { title, .. } | Tag::Image { title, .. } = &tag {
+                    self.attribute(title, false);
+                }
 
+                if let Tag::Link {
+                    link_type: LinkType::WikiLink { .. },
+                    dest_url,
+                    ..
+                } = &tag
+                {
+                    if self.state.markdown_depth == 0 {
+                        self.report.add_wikilink(dest_url);
+                    }
+                }
+
+                if let Tag::CodeBlock(kind) = &tag {
+                    let language = match kind {
+                        CodeBlockKind::Fenced(info) => code_language(info),
+                        CodeBlockKind::Indented => None,
+                    };
+
+                    if self.config.markdown_code_blocks
+                        && matches!(language.as_deref(), Some("markdown" | "md"))
+                    {
+                        self.state.markdown_block = Some(String::new());
+                    }
+
+                    // A code block inside a Markdown code block is part of it,
+                    // and is tallied under its language.
+                    let language = if self.state.markdown_depth == 0 {
+                        language.map(|language| self.report.add_code_language(language))
+                    } else {
+                        self.state.code_languages.last().copied().flatten()
+                    };
+                    self.state.code_languages.push(language);
+                }
+
+                self.state.start(tag);
+            }
+
+            End(tag) => {
+                if !is_inline_end(&tag) {
+                    self.inline.flush(&mut self.report);
+                    self.state.html.end_inline();
+                }
+
+                if tag == TagEnd::HtmlBlock {
+                    let html = std::mem::take(&mut self.state.html_block);
+                    self.html(&html, true);
+                    self.inline.flush(&mut self.report);
+
+                    self.state.html.end_block();
+                }
+
+                if let Some(markdown) = self.state.markdown_block.take() {
+                    self.markdown(&markdown);
+                }
+
+                self.state.end();
+            }
+
+            // An HTML block arrives a line at a time, and a single element can
+            // span many lines, so buffer the whole block before handling it.
             Html(html) => {
-                if options.contains(Options::IncludeBlockHtml) {
-                    for token in Tokenizer::from(html.as_ref()).flatten() {
-                        if let Token::Text { text } = token {
-                            count += text.unicode_words().count() as u64;
+                if self.state.in_html_block() {
+                    self.state.html_block.push_str(&html);
+                } else {
+                    self.html(&html, true);
+                }
+            }
+
+            InlineHtml(html) => self.html(&html, false),
+
+            InlineMath(expression) => {
+                self.inline.boundary();
+
+                let attribution = self.attribution(TextKind::InlineMath, &expression);
+                let words = math_words(&expression, self.config.math);
+                self.report.record(attribution, words);
+            }
+
+            DisplayMath(expression) => {
+                self.inline.boundary();
+
+                let attribution = self.attribution(TextKind::DisplayMath, &expression);
+                let words = math_words(&expression, self.config.math);
+                self.report.record(attribution, words);
+            }
+
+            SoftBreak => self.inline.soft_break(),
+
+            // None of these contribute to the final count, but they do separate
+            // the words on either side of them.
+            FootnoteReference(_) | HardBreak => self.inline.boundary(),
+            TaskListMarker(checked) => {
+                self.inline.boundary();
+                self.state.mark_task(checked);
+            }
+            Rule => self.inline.flush(&mut self.report),
+        }
+    }
+
+    /// Count the contents of a Markdown code block as Markdown, as if it were
+    /// nested inside the code block.
+    fn markdown(&mut self, markdown: &str) {
+        self.state.markdown_depth += 1;
+        let events = parser(markdown, self.config)
+            .map(Event::into_static)
+            .collect::<Vec<_>>();
+        let closed_later = self.state.html.read_ahead(html::closed_later(&events));
+        for event in events {
+            self.header_event(event);
+        }
+        self.state.html.read_ahead(closed_later);
+        self.inline.flush(&mut self.report);
+        self.state.markdown_depth -= 1;
+    }
+
+    fn finish(mut self) -> CountReport {
+        // A document can only end in the middle of a heading or table header if
+        // the parser stopped early, but count whatever was in it anyway.
+        for event in self.pending.take().unwrap_or_default() {
+            self.count(event);
+        }
+
+        self.inline.flush(&mut self.report);
+        self.report.finish();
+        self.report
+    }
+
+    /// Note the start of a new section, given the events of its heading, and
+    /// whether it is excluded or selected.
+    fn start_section(&mut self, level: HeadingLevel, heading: &[Event<'a>]) {
+        let title = heading
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+
+        let excluded = self
+            .config
+            .exclude_sections
+            .iter()
+            .any(|matcher| matcher.matches(level, &title));
+        self.state.start_section(level, title);
+
+        if excluded {
+            self.state.excluded_section.get_or_insert(level);
+        }
+
+        let selected = self
+            .config
+            .section
+            .as_ref()
+            .is_some_and(|selector| selector.matches(&self.state.headings));
+        if selected {
+            self.state.selected_section.get_or_insert(level);
+        }
+    }
+
+    /// Whether (and why) some text, at the current point in the document,
+    /// should be counted.
+    fn attribution(&mut self, kind: TextKind, text: &str) -> Attribution {
+        let categories = self.state.categories().with_kind(kind);
+        // Code in the languages counted as prose is filtered as if it were
+        // prose, but still tallied as code.
+        let filtered = if self.in_prose_code() {
+            categories.without(Category::BlockCode)
+        } else {
+            categories
+        };
+        let context = Context {
+            tags: &self.state.stack,
+            kind,
+            categories: filtered,
+        };
+
+        let selected = |selectors: &[Selector]| {
+            selectors
+                .iter()
+                .any(|selector| selector.matches(&self.state.stack))
+        };
+
+        let filtered_out =
+            !(self.filter.includes(&context, text) || selected(&self.config.include));
+
+        // Only the categories whose options left the text out are charged for
+        // it. If the filter left it out for some other reason, that is charged
+        // to the filter itself, like every other rule.
+        let excluded_as = filtered
+            .iter()
+            .filter(|category| {
+                !self.config.counts(*category)
+                    || filtered_out && !self.config.options.contains(category.option())
+            })
+            .collect();
+
+        let rules = [
+            (
+                Rule::Filter,
+                filtered_out && filtered.allowed_by(&self.config.options),
+            ),
+            (Rule::Selector, selected(&self.config.exclude)),
+            (Rule::Html, self.state.html.in_excluded()),
+            (
+                Rule::ListDepth,
+                self.config
+                    .max_list_depth
+                    .is_some_and(|max| self.state.list_depth() > max),
+            ),
+            (
+                Rule::BlockquoteDepth,
+                self.config
+                    .max_blockquote_depth
+                    .is_some_and(|max| self.state.quote_depth() > max),
+            ),
+            (
+                Rule::HeadingLevel,
+                self.config
+                    .max_heading_level
+                    .is_some_and(|max| self.state.heading_level().is_some_and(|level| level > max)),
+            ),
+            (
+                Rule::Section,
+                self.state.excluded_section.is_some()
+                    || self.config.section.is_some() && self.state.selected_section.is_none(),
+            ),
+            (
+                Rule::Alert,
+                self.state.in_alert(&self.config.exclude_alerts),
+            ),
+            (
+                Rule::TableRows,
+                !self.state.in_table_rows(self.config.table_rows),
+            ),
+            (Rule::Column, self.state.in_excluded_column()),
+        ];
+        let excluded_by = rules
+            .into_iter()
+            .filter(|(_, excluded)| *excluded)
+            .map(|(rule, _)| rule)
+            .collect();
+
+        self.state.attribution(categories, excluded_as, excluded_by)
+    }
+
+    /// Whether we are in a fenced code block in one of the languages which
+    /// are counted like prose.
+    fn in_prose_code(&self) -> bool {
+        self.state.stack.iter().any(|tag| match tag {
+            Tag::CodeBlock(CodeBlockKind::Fenced(info)) => {
+                code_language(info).is_some_and(|language| {
+                    self.config
+                        .code_languages
+                        .iter()
+                        .any(|expected| expected.eq_ignore_ascii_case(&language))
+                })
+            }
+            _ => false,
+        })
+    }
+
+    /// Add some prose to the inline text. Bare URLs are only picked out of it
+    /// once it is split into words, since a URL can run on into the text after
+    /// it, but note how any URL which starts here is counted.
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let attribution = self.attribution(TextKind::Text, text);
+        let Some(url) = url::find(text).into_iter().next() else {
+            self.inline.push(text, attribution);
+            return;
+        };
+
+        // A URL never joins up with the text before it, e.g. in `*see*https://…`.
+        if url.start == 0 {
+            self.inline.boundary();
+        }
+
+        let url = self.attribution(TextKind::Url, &text[url]);
+        self.inline.push_with_url(text, attribution, url);
+    }
+
+    /// Count the URL of an autolink as a single word or, with
+    /// [`UrlMode::Segments`], as each of the words in it. Either way, it never joins up with the text around
+    /// it.
+    fn url(&mut self, url: &str) {
+        self.inline.boundary();
+
+        let attribution = self.attribution(TextKind::Url, url);
+        if self.config.urls == UrlMode::Segments {
+            self.inline.push(url, attribution);
+            self.inline.boundary();
+        } else {
+            self.report.record(attribution, 1);
+        }
+    }
+
+    /// Add the text of a title or an HTML `<img>` attribute, which never joins
+    /// up with the text around it. Attributes of an excluded element are excluded
+    /// along with its contents.
+    fn attribute(&mut self, text: &str, excluded: bool) {
+        if text.is_empty() {
+            return;
+        }
+
+        let mut attribution = self.attribution(TextKind::Attribute, text);
+        if excluded {
+            attribution = self.state.attribution(
+                attribution.categories,
+                attribution.excluded_as,
+                attribution.excluded_by.with(Rule::Html),
+            );
+        }
+
+        self.inline.boundary();
+        self.inline.push(text, attribution);
+        self.inline.boundary();
+    }
+
+    /// Track the elements opened and closed by some HTML, and add any prose in
+    /// it to the inline text. Elements like `<em>` can appear in the middle of
+    /// a word (just like `*` can), but any other element separates words.
+    /// Comments may be [`Directive`]s.
+    fn html(&mut self, html: &str, from_block: bool) {
+        for token in Tokenizer::new(html) {
+            match token {
+                Token::StartTag {
+                    name,
+                    attributes,
+                    self_closing,
+                } => {
+                    if !html::is_phrasing(&name) {
+                        self.inline.boundary();
+                    }
+
+                    let excluded = self
+                        .config
+                        .exclude_html
+                        .iter()
+                        .any(|matcher| matcher.matches(&name, &attributes));
+
+                    if name == "img" && !self.state.html.in_non_prose() {
+                        for (attribute, value) in &attributes {
+                            if attribute == "alt" || attribute == "title" {
+                                self.attribute(value, excluded);
+                            }
                         }
                     }
+
+                    self.state
+                        .html
+                        .start(name, self_closing, from_block, excluded);
                 }
-            }
 
-            // TODO: add support for these in some sensible-ish way!
-            InlineMath(_) => { /* unimplemented */ }
-            DisplayMath(_) => { /* unimplemented */ }
+                Token::EndTag { name } => {
+                    if !html::is_phrasing(&name) {
+                        self.inline.boundary();
+                    }
+                    self.state.html.end(&name);
+                }
 
-            // None of these contribute to the final count.
-            InlineHtml(_tag) => {}
-            FootnoteReference(_) => {}
-            SoftBreak => {}
-            HardBreak => {}
-            Rule => {}
-            TaskListMarker(_) => {}
+                Token::Text(text) => {
+                    if !self.state.html.in_non_prose() {
+                        self.text(&text);
+                    }
+                }
+
+                // Directives in a Markdown code block are only examples.
+                Token::Comment(_) if self.state.markdown_depth > 0 => {}
+                Token::Comment(comment) => match Directive::parse(comment) {
+                    Some(Directive::Off) if self.state.off.is_none() => {
+                        self.state.off = Some(self.report.add_directive(Directive::Off));
+                    }
+                    Some(Directive::On) => self.state.off = None,
+                    Some(Directive::IgnoreNext) => {
+                        let directive = self.report.add_directive(Directive::IgnoreNext);
+                        self.state.ignore_next = Some(directive);
+                    }
+                    Some(Directive::Off) | None => {}
+                },
+            }
         }
     }
+}
 
-    count
+/// Whether a tag is an inline (span-level) tag, which can appear in the middle
+/// of a word, rather than a block-level one, which always separates words.
+fn is_inline(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Superscript
+            | Tag::Subscript
+            | Tag::Link { .. }
+            | Tag::Image { .. }
+    )
 }
 
-pub struct State {
-    in_code_block: bool,
-    blockquote_level: u8,
-    in_metadata_block: bool,
-    in_footnote: bool,
-    in_table: bool,
-    in_heading: bool,
+/// The equivalent of [`is_inline`] for the end of a tag.
+fn is_inline_end(tag: &TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::Emphasis
+            | TagEnd::Strong
+            | TagEnd::Strikethrough
+            | TagEnd::Superscript
+            | TagEnd::Subscript
+            | TagEnd::Link
+            | TagEnd::Image
+    )
 }
 
-impl State {
-    fn allowed_for(&self, options: &Options) -> bool {
-        (!self.in_code_block || options.contains(Options::IncludeBlockCode))
-            && (!self.in_blockquote() || options.contains(Options::IncludeBlockquotes))
-            && (!self.in_metadata_block || options.contains(Options::IncludeMetadata))
-            && (!self.in_footnote || options.contains(Options::IncludeFootnotes))
-            && (!self.in_table || options.contains(Options::IncludeTables))
-            && (!self.in_heading || options.contains(Options::IncludeHeadings))
+/// How many words a math expression contributes: one for the whole expression,
+/// or, with [`MathMode::TextOnly`], the words in its `\text{…}` commands.
+fn math_words(expression: &str, mode: MathMode) -> u64 {
+    match mode {
+        MathMode::TextOnly => math::text_words(expression),
+        MathMode::Ignore | MathMode::Expression => 1,
     }
+}
+
+/// The language of a fenced code block: the first word of its info string, in
+/// lowercase, like `rust` for both ` ```Rust ` and ` ```rust,ignore `.
+pub(crate) fn code_language(info: &str) -> Option<String> {
+    info.split(|c: char| c.is_whitespace() || c == ',')
+        .find(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+}
 
-    #[inline(always)]
-    fn in_blockquote(&self) -> bool {
-        self.blockquote_level > 0
+/// Where the parser is in the document: which Markdown tags and HTML elements
+/// it is currently inside of, and which directives are in effect.
+pub struct State<'a> {
+    /// Every Markdown tag which is currently open, from outermost to innermost.
+    stack: Vec<Tag<'a>>,
+    /// The contents of the HTML block currently being read.
+    html_block: String,
+    html: Elements,
+    /// The `off` directive currently in effect, if any.
+    off: Option<usize>,
+    /// An `ignore-next` directive which applies to the next block to start.
+    ignore_next: Option<usize>,
+    /// The `ignore-next` directive which applies to the current block, and the
+    /// depth of the stack when that block started.
+    ignoring: Option<(usize, usize)>,
+    /// For each open list item, whether it is a checked or unchecked task.
+    items: Vec<Option<bool>>,
+    /// The level and text of the heading of each section we are in, from
+    /// outermost to innermost.
+    headings: Vec<(HeadingLevel, String)>,
+    /// The level of the heading of the excluded section we are in, if any.
+    excluded_section: Option<HeadingLevel>,
+    /// The level of the heading of the selected section we are in, if any.
+    selected_section: Option<HeadingLevel>,
+    /// The position of the table cell we are in (or were most recently in),
+    /// counting from 1.
+    column: usize,
+    /// The positions of the columns of the current table which are excluded.
+    excluded_columns: Vec<usize>,
+    /// For each open code block, the index of its language, if it has one.
+    code_languages: Vec<Option<usize>>,
+    /// The contents of the Markdown code block currently being read, with
+    /// [`Config::markdown_code_blocks`].
+    markdown_block: Option<String>,
+    /// How many Markdown code blocks deep we are, counting them as Markdown.
+    markdown_depth: usize,
+}
+
+impl<'a> State<'a> {
+    fn new() -> State<'a> {
+        State {
+            stack: Vec::new(),
+            html_block: String::new(),
+            html: Elements::default(),
+            off: None,
+            ignore_next: None,
+            ignoring: None,
+            items: Vec::new(),
+            headings: Vec::new(),
+            excluded_section: None,
+            selected_section: None,
+            column: 0,
+            excluded_columns: Vec::new(),
+            code_languages: Vec::new(),
+            markdown_block: None,
+            markdown_depth: 0,
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'a>) {
+        if !is_inline(&tag) {
+            if let Some(directive) = self.ignore_next.take() {
+                self.ignoring.get_or_insert((self.stack.len(), directive));
+            }
+        }
+
+        match tag {
+            Tag::Item => self.items.push(None),
+            Tag::TableHead | Tag::TableRow => self.column = 0,
+            Tag::TableCell => self.column += 1,
+            _ => {}
+        }
+
+        self.stack.push(tag);
+    }
+
+    fn end(&mut self) {
+        match self.stack.pop() {
+            Some(Tag::Item) => {
+                self.items.pop();
+            }
+            Some(Tag::CodeBlock(_)) => {
+                self.code_languages.pop();
+            }
+            _ => {}
+        }
+
+        if self
+            .ignoring
+            .is_some_and(|(depth, _)| depth == self.stack.len())
+        {
+            self.ignoring = None;
+        }
+    }
+
+    /// Whether any of the open tags matches the predicate, i.e. whether we are
+    /// anywhere inside some kind of content, however deeply nested.
+    fn inside(&self, predicate: impl Fn(&Tag<'a>) -> bool) -> bool {
+        self.stack.iter().any(predicate)
+    }
+
+    /// Whether we are in a heading which is not inside anything else, and so
+    /// starts a section.
+    fn in_top_level_heading(&self) -> bool {
+        matches!(self.stack.first(), Some(Tag::Heading { .. }))
+    }
+
+    /// The level of the heading we are in, if any.
+    fn heading_level(&self) -> Option<HeadingLevel> {
+        self.stack.iter().find_map(|tag| match tag {
+            Tag::Heading { level, .. } => Some(*level),
+            _ => None,
+        })
+    }
+
+    /// A heading ends every section with a heading of the same or a lower
+    /// level (i.e. the same or more `#`s), and starts a new one.
+    fn start_section(&mut self, level: HeadingLevel, title: String) {
+        while self
+            .headings
+            .last()
+            .is_some_and(|(open_level, _)| *open_level >= level)
+        {
+            self.headings.pop();
+        }
+        self.headings.push((level, title));
+
+        if self.excluded_section.is_some_and(|open| open >= level) {
+            self.excluded_section = None;
+        }
+
+        if self.selected_section.is_some_and(|open| open >= level) {
+            self.selected_section = None;
+        }
+    }
+
+    /// How many lists deep we are: 1 in a top-level list, 2 in a list nested in
+    /// that, and so on.
+    fn list_depth(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|tag| matches!(tag, Tag::List(_)))
+            .count()
+    }
+
+    /// Whether we are in the given rows of a table, or not in a table at all.
+    fn in_table_rows(&self, rows: TableRows) -> bool {
+        match rows {
+            TableRows::All => true,
+            TableRows::Header => !self.inside(|tag| matches!(tag, Tag::TableRow)),
+            TableRows::Body => !self.inside(|tag| matches!(tag, Tag::TableHead)),
+        }
+    }
+
+    fn in_excluded_column(&self) -> bool {
+        self.inside(|tag| matches!(tag, Tag::TableCell))
+            && self.excluded_columns.contains(&self.column)
+    }
+
+    /// Whether we are inside an alert of any of the given kinds.
+    fn in_alert(&self, kinds: &[BlockQuoteKind]) -> bool {
+        self.inside(|tag| matches!(tag, Tag::BlockQuote(Some(kind)) if kinds.contains(kind)))
+    }
+
+    /// How many blockquotes deep we are: 1 in a blockquote, 2 in a blockquote
+    /// nested in that, and so on. Alerts are not blockquotes, so they do not
+    /// count toward this.
+    fn quote_depth(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|tag| matches!(tag, Tag::BlockQuote(None)))
+            .count()
+    }
+
+    /// Note that the current list item is a task, from its `[ ]` or `[x]`.
+    fn mark_task(&mut self, checked: bool) {
+        if let Some(item) = self.items.last_mut() {
+            *item = Some(checked);
+        }
+    }
+
+    fn in_html_block(&self) -> bool {
+        self.inside(|tag| matches!(tag, Tag::HtmlBlock))
+    }
+
+    /// Whether we are in an autolink like `<https://example.com>`, whose text
+    /// is the URL itself.
+    fn in_autolink(&self) -> bool {
+        self.stack.last().is_some_and(|tag| {
+            matches!(
+                tag,
+                Tag::Link {
+                    link_type: LinkType::Autolink | LinkType::Email,
+                    ..
+                }
+            )
+        })
+    }
+
+    /// Whether (and why) text in the given categories should be counted, given
+    /// the categories and rules which exclude it: text which would be counted
+    /// may still be excluded by a directive.
+    fn attribution(
+        &self,
+        categories: Categories,
+        excluded_as: Categories,
+        excluded_by: Rules,
+    ) -> Attribution {
+        let allowed = excluded_as.is_empty() && excluded_by.is_empty();
+        let directive = self
+            .off
+            .or(self.ignoring.map(|(_, directive)| directive))
+            .filter(|_| allowed);
+
+        Attribution {
+            categories,
+            included: allowed && directive.is_none(),
+            excluded_as,
+            excluded_by,
+            directive,
+            heading: self.heading_level(),
+            quote_depth: self.quote_depth(),
+            code_language: self.code_languages.last().copied().flatten(),
+        }
+    }
+
+    /// The categories of content the parser is currently inside of.
+    fn categories(&self) -> Categories {
+        let mut categories = self
+            .stack
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::CodeBlock(_) => Some(Category::BlockCode),
+                Tag::BlockQuote(None) => Some(Category::Blockquotes),
+                Tag::BlockQuote(Some(_)) => Some(Category::Alerts),
+                Tag::Strikethrough => Some(Category::Strikethrough),
+                Tag::Superscript => Some(Category::Superscript),
+                Tag::Subscript => Some(Category::Subscript),
+                Tag::MetadataBlock(_) => Some(Category::Metadata),
+                Tag::FootnoteDefinition(_) => Some(Category::Footnotes),
+                Tag::Table(_) => Some(Category::Tables),
+                Tag::Heading { .. } => Some(Category::Headings),
+                Tag::HtmlBlock => Some(Category::BlockHtml),
+                Tag::Link {
+                    link_type: LinkType::Autolink | LinkType::Email,
+                    ..
+                } => None,
+                Tag::Link {
+                    link_type: LinkType::WikiLink { .. },
+                    ..
+                } => Some(Category::Wikilinks),
+                Tag::Link { .. } => Some(Category::LinkText),
+                Tag::Image { .. } => Some(Category::ImageText),
+                Tag::DefinitionListTitle => Some(Category::DefinitionTitles),
+                Tag::DefinitionListDefinition => Some(Category::Definitions),
+                Tag::List(Some(_)) => Some(Category::OrderedLists),
+                Tag::List(None) => Some(Category::UnorderedLists),
+                _ => None,
+            })
+            .chain(self.items.iter().filter_map(|item| match item {
+                Some(true) => Some(Category::CheckedTasks),
+                Some(false) => Some(Category::UncheckedTasks),
+                None => None,
+            }))
+            .collect::<Categories>();
+
+        if self.html.in_block() {
+            categories = categories.with(Category::BlockHtml);
+        }
+
+        categories
     }
 }
 
 bitflags! {
     #[repr(transparent)]
-    #[derive(Copy, Clone, PartialEq, Eq)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
     pub struct Options: u16 {
         const IncludeInlineCode =  1;
         const IncludeBlockCode =   1 << 2;
@@ -136,6 +932,11 @@ bitflags! {
         const IncludeMetadata =    1 << 7;
         const IncludeHeadings =    1 << 8;
 
+        /// Include GitHub-style alerts like `> [!NOTE]`. These are not
+        /// blockquotes, so [`Options::IncludeBlockquotes`] does not affect
+        /// them (except for actual blockquotes inside them).
+        const IncludeAlerts =      1 << 9;
+
         const DEFAULT =
               Options::IncludeInlineCode.bits()
             | Options::IncludeTables.bits()
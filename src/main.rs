@@ -3,10 +3,14 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, Parser, ValueEnum};
+use pulldown_cmark::{BlockQuoteKind, HeadingLevel, Options as CmarkOptions};
 use rayon::prelude::*;
 
-use count_md::{count_with_options, Options};
+use count_md::{
+    report_with_config, ColumnMatcher, Config, CountReport, Dialect, HeadingMatcher, HtmlMatcher,
+    MathMode, Options, Section, SectionSelector, Selector, TableRows, UrlMode,
+};
 
 fn main() -> Result<(), Error> {
     let args = Args::parse();
@@ -39,7 +43,7 @@ fn main() -> Result<(), Error> {
             .collect::<Result<Vec<_>, Error>>()?,
     };
 
-    let resolved_options = options_from(&args);
+    let config = config_from(&args);
 
     // This can be multithreaded, using Rayon to parallelize the counting. That
     // should make it *much* faster, since right now it is single-threaded.
@@ -48,37 +52,38 @@ fn main() -> Result<(), Error> {
         .fold(
             || (0, vec![]),
             |(sum, mut pairs), (path, content)| {
-                let count = count_with_options(content, resolved_options);
-                let new_sum = sum + count;
-                pairs.push((path, count));
+                let report = report_with_config(content, &config);
+                let new_sum = sum + report.total();
+                pairs.push((path, report));
                 (new_sum, pairs)
             },
         )
         .reduce(
             || (0, vec![]),
             |(total, mut pairs), (subtotal, subpairs)| {
-                // This copy should be quite cheap: it copies a reference and a
-                // `u64` from `subpairs` into `pairs`. It will be O(N) on the
-                // size of the `subpairs`.
+                // This move should be quite cheap: it moves a reference and a
+                // `CountReport` from `subpairs` into `pairs`. It will be O(N)
+                // on the size of the `subpairs`.
                 //
                 // With enough elements, that could be noticeable. That is the
                 // tradeoff for parallelizing this! However, in most cases, the
                 // number of files in question will be relatively small; even
                 // with *thousands* of files, this should be very fast.
-                pairs.extend(&subpairs);
+                pairs.extend(subpairs);
                 (total + subtotal, pairs)
             },
         );
 
-    report(pairs, total, output)
+    report(pairs, total, &args, output)
 }
 
 // This could in principle be async, but it would not much matter from what I
 // can see: it needs to report and flush *all* of the data. (Test it, of course,
 // just to be sure!)
 fn report(
-    pairs: Vec<(&impl std::fmt::Display, u64)>,
+    pairs: Vec<(&impl std::fmt::Display, CountReport)>,
     total: u64,
+    args: &Args,
     output: Output,
 ) -> Result<(), Error> {
     let (dest, mut buf) = match output {
@@ -86,11 +91,25 @@ fn report(
         Output::Stdout(stdout) => (String::from("<stdout>"), stdout),
     };
 
-    for (path, count) in pairs {
-        writeln!(buf, "{path} has {count} words").map_err(|source| Error::Write {
+    for (path, report) in pairs {
+        let count = report.total();
+        let details = if args.breakdown {
+            describe(&report)
+        } else {
+            String::new()
+        };
+
+        writeln!(buf, "{path} has {count} words{details}").map_err(|source| Error::Write {
             dest: dest.clone(),
             source,
         })?;
+
+        if args.outline {
+            write_outline(&mut buf, &report).map_err(|source| Error::Write {
+                dest: dest.clone(),
+                source,
+            })?;
+        }
     }
 
     writeln!(buf, "Total: {total}").map_err(|source| Error::Write {
@@ -104,6 +123,113 @@ fn report(
     Ok(())
 }
 
+/// Describe where the words in a report came from, e.g. " (1100 in footnotes,
+/// 300 excluded as blockquotes, 25 excluded by list depth, 40 excluded by
+/// directive 1 (count-md: off))".
+/// Empty if there is nothing to describe.
+fn describe(report: &CountReport) -> String {
+    let included = report
+        .categories()
+        .filter(|(_, tally)| tally.included > 0)
+        .map(|(category, tally)| format!("{} in {category}", tally.included));
+
+    let excluded = report
+        .categories()
+        .filter(|(_, tally)| tally.excluded > 0)
+        .map(|(category, tally)| format!("{} excluded as {category}", tally.excluded));
+
+    let heading_levels = report
+        .heading_levels()
+        .filter(|(_, tally)| tally.included > 0)
+        .map(|(level, tally)| format!("{} in {level} headings", tally.included));
+
+    let quote_depths = report
+        .quote_depths()
+        .filter(|(_, tally)| tally.included > 0)
+        .map(|(depth, tally)| format!("{} in blockquotes at depth {depth}", tally.included));
+
+    let code_languages = report
+        .code_languages()
+        .filter(|(_, tally)| tally.included > 0)
+        .map(|(language, tally)| format!("{} in {language} code blocks", tally.included));
+
+    let rules = report
+        .rules()
+        .map(|(rule, excluded)| format!("{excluded} excluded by {rule}"));
+
+    let directives = report
+        .directives()
+        .iter()
+        .enumerate()
+        .map(|(index, tally)| {
+            format!(
+                "{} excluded by directive {} ({})",
+                tally.excluded,
+                index + 1,
+                tally.directive
+            )
+        });
+
+    let parts = included
+        .chain(heading_levels)
+        .chain(quote_depths)
+        .chain(code_languages)
+        .chain(excluded)
+        .chain(rules)
+        .chain(directives)
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join(", "))
+    }
+}
+
+/// Write the sections of a report as an indented tree, e.g.:
+///
+/// ```text
+///   (before the first heading): 12 words
+///   # Introduction: 340 words (120 in the section itself)
+///     ## Background: 220 words
+/// ```
+fn write_outline(buf: &mut impl Write, report: &CountReport) -> io::Result<()> {
+    fn write_section(buf: &mut impl Write, section: &Section, depth: usize) -> io::Result<()> {
+        let indent = "  ".repeat(depth);
+        let hashes = "#".repeat(section.level as usize);
+        let title = section.title.trim();
+        let total = section.total();
+        if section.subsections.is_empty() {
+            writeln!(buf, "{indent}{hashes} {title}: {total} words")?;
+        } else {
+            let own = section.words;
+            writeln!(
+                buf,
+                "{indent}{hashes} {title}: {total} words ({own} in the section itself)"
+            )?;
+        }
+
+        for subsection in &section.subsections {
+            write_section(buf, subsection, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    if report.preamble() > 0 {
+        writeln!(
+            buf,
+            "  (before the first heading): {} words",
+            report.preamble()
+        )?;
+    }
+
+    for section in report.sections() {
+        write_section(buf, section, 1)?;
+    }
+
+    Ok(())
+}
+
 // Note: this might be able to be eliminated entirely, since there is only the
 // one variant and I am otherwise just dumping strings.
 #[derive(Debug, thiserror::Error)]
@@ -165,6 +291,57 @@ impl std::fmt::Display for FileOpenReason {
     }
 }
 
+fn config_from(args: &Args) -> Config {
+    let mut config = Config::from(options_from(args));
+    // `--all` counts math, but as whole expressions, not just their text.
+    config.math = if args.all {
+        MathMode::Expression
+    } else {
+        args.math.mode()
+    };
+    config.urls = args.urls.mode();
+    config.image_text = args.image_text;
+    config.attributes = args.attributes;
+    config.definition_titles = args.definition_titles;
+    config.definitions = args.definitions;
+    config.ordered_lists = args.ordered_lists;
+    config.unordered_lists = args.unordered_lists;
+    config.checked_tasks = matches!(args.tasks, Tasks::All | Tasks::Checked);
+    config.unchecked_tasks = matches!(args.tasks, Tasks::All | Tasks::Unchecked);
+    config.strikethrough = args.strikethrough;
+    config.superscript = args.superscript;
+    config.subscript = args.subscript;
+    config.wikilinks = args.wikilinks;
+    config.table_rows = args.table_rows.rows();
+    config.exclude_columns = args.exclude_column.clone();
+    config.include = args.include.clone();
+    config.exclude = args.exclude.clone();
+    config.exclude_html = args.exclude_html.clone();
+    config.max_list_depth = args.max_list_depth;
+    config.max_blockquote_depth = args.max_blockquote_depth;
+    config.max_heading_level = args
+        .max_heading_level
+        .and_then(|level| HeadingLevel::try_from(usize::from(level)).ok());
+    config.exclude_sections = args.exclude_section.clone();
+    config.section = args.section.clone();
+    config.code_languages = args.code_language.clone();
+    config.markdown_code_blocks = args.markdown_code_blocks;
+    config.exclude_alerts = args
+        .exclude_alerts
+        .iter()
+        .map(|alert| alert.kind())
+        .collect();
+
+    config.extensions = args.dialect.dialect().extensions();
+    for extension in &args.enable {
+        config.extensions.insert(extension.option());
+    }
+    for extension in &args.disable {
+        config.extensions.remove(extension.option());
+    }
+    config
+}
+
 fn options_from(args: &Args) -> Options {
     if args.all {
         return Options::all();
@@ -204,14 +381,189 @@ fn options_from(args: &Args) -> Options {
         options |= Options::IncludeBlockHtml;
     }
 
+    if args.alerts {
+        options |= Options::IncludeAlerts;
+    }
+
     options
 }
 
+/// How to count math expressions.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Math {
+    /// Do not count math at all.
+    Ignore,
+    /// Count each math expression as a single word.
+    Expression,
+    /// Count only the words in `\text{…}`-style commands in math.
+    Text,
+}
+
+impl Math {
+    fn mode(self) -> MathMode {
+        match self {
+            Math::Ignore => MathMode::Ignore,
+            Math::Expression => MathMode::Expression,
+            Math::Text => MathMode::TextOnly,
+        }
+    }
+}
+
+/// The flavor of Markdown to parse documents as.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum MarkdownDialect {
+    /// Plain CommonMark, with no extensions.
+    Commonmark,
+    /// GitHub Flavored Markdown: tables, strikethrough, task lists, footnotes,
+    /// and alerts.
+    Gfm,
+    /// Every extension the parser supports.
+    All,
+}
+
+impl MarkdownDialect {
+    fn dialect(self) -> Dialect {
+        match self {
+            MarkdownDialect::Commonmark => Dialect::CommonMark,
+            MarkdownDialect::Gfm => Dialect::Gfm,
+            MarkdownDialect::All => Dialect::All,
+        }
+    }
+}
+
+/// The Markdown extensions which can be turned on or off individually.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Extension {
+    Tables,
+    Footnotes,
+    Strikethrough,
+    Tasklists,
+    SmartPunctuation,
+    HeadingAttributes,
+    /// YAML metadata blocks, delimited by `---`.
+    YamlMetadata,
+    /// TOML metadata blocks, delimited by `+++`.
+    TomlMetadata,
+    Math,
+    /// GitHub-style alerts like `> [!NOTE]`.
+    Alerts,
+    DefinitionLists,
+    Superscript,
+    Subscript,
+    Wikilinks,
+}
+
+impl Extension {
+    fn option(self) -> CmarkOptions {
+        match self {
+            Extension::Tables => CmarkOptions::ENABLE_TABLES,
+            Extension::Footnotes => CmarkOptions::ENABLE_FOOTNOTES,
+            Extension::Strikethrough => CmarkOptions::ENABLE_STRIKETHROUGH,
+            Extension::Tasklists => CmarkOptions::ENABLE_TASKLISTS,
+            Extension::SmartPunctuation => CmarkOptions::ENABLE_SMART_PUNCTUATION,
+            Extension::HeadingAttributes => CmarkOptions::ENABLE_HEADING_ATTRIBUTES,
+            Extension::YamlMetadata => CmarkOptions::ENABLE_YAML_STYLE_METADATA_BLOCKS,
+            Extension::TomlMetadata => CmarkOptions::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS,
+            Extension::Math => CmarkOptions::ENABLE_MATH,
+            Extension::Alerts => CmarkOptions::ENABLE_GFM,
+            Extension::DefinitionLists => CmarkOptions::ENABLE_DEFINITION_LIST,
+            Extension::Superscript => CmarkOptions::ENABLE_SUPERSCRIPT,
+            Extension::Subscript => CmarkOptions::ENABLE_SUBSCRIPT,
+            Extension::Wikilinks => CmarkOptions::ENABLE_WIKILINKS,
+        }
+    }
+}
+
+/// Which rows of tables to count.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Rows {
+    /// Every row.
+    All,
+    /// Only the header row.
+    Header,
+    /// Only the rows after the header row.
+    Body,
+}
+
+impl Rows {
+    fn rows(self) -> TableRows {
+        match self {
+            Rows::All => TableRows::All,
+            Rows::Header => TableRows::Header,
+            Rows::Body => TableRows::Body,
+        }
+    }
+}
+
+/// The kinds of GitHub-style alerts, e.g. `> [!NOTE]`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Alert {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl Alert {
+    fn kind(self) -> BlockQuoteKind {
+        match self {
+            Alert::Note => BlockQuoteKind::Note,
+            Alert::Tip => BlockQuoteKind::Tip,
+            Alert::Important => BlockQuoteKind::Important,
+            Alert::Warning => BlockQuoteKind::Warning,
+            Alert::Caution => BlockQuoteKind::Caution,
+        }
+    }
+}
+
+/// Which task list items to count.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Tasks {
+    /// Count every task.
+    All,
+    /// Count only checked tasks, `- [x]`.
+    Checked,
+    /// Count only unchecked tasks, `- [ ]`.
+    Unchecked,
+    /// Do not count tasks at all.
+    Exclude,
+}
+
+/// How to count URLs.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Urls {
+    /// Do not count URLs at all.
+    Exclude,
+    /// Count each URL as a single word.
+    Word,
+    /// Count each of the words in a URL.
+    Segments,
+}
+
+impl Urls {
+    fn mode(self) -> UrlMode {
+        match self {
+            Urls::Exclude => UrlMode::Exclude,
+            Urls::Word => UrlMode::Word,
+            Urls::Segments => UrlMode::Segments,
+        }
+    }
+}
+
 #[derive(Parser)]
 struct Args {
     #[clap(flatten)]
     paths: Paths,
 
+    /// Show how many words were included or excluded from each kind of content.
+    #[arg(long)]
+    breakdown: bool,
+
+    /// Show the words in each section of each file, nested by heading level.
+    #[arg(long)]
+    outline: bool,
+
     /// Include every possible option.
     #[clap(
         long,
@@ -223,7 +575,9 @@ struct Args {
             "tables",
             "inline_code",
             "block_code",
-            "block_html"
+            "block_html",
+            "alerts",
+            "math"
         ]
     )]
     all: bool,
@@ -250,6 +604,26 @@ struct Args {
     )]
     blockquotes: bool,
 
+    /// Include GitHub-style alerts like `> [!NOTE]`.
+    #[clap(
+        long,
+        default_value = "false",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    alerts: bool,
+
+    /// Never count alerts of this kind, even with `--alerts`. May be repeated.
+    #[clap(long, value_enum, value_name = "KIND", require_equals(true))]
+    exclude_alerts: Vec<Alert>,
+
+    /// Only count blockquotes nested at most this deep: 1 counts quotes, but
+    /// not quotes inside quotes.
+    #[clap(long, value_name = "DEPTH", require_equals(true))]
+    max_blockquote_depth: Option<usize>,
+
     /// Include headings.
     #[clap(
         long,
@@ -261,6 +635,16 @@ struct Args {
     )]
     headings: bool,
 
+    /// Only count headings up to this level: 2 counts `#` and `##` headings,
+    /// but not `###` through `######`.
+    #[clap(
+        long,
+        value_name = "LEVEL",
+        value_parser = clap::value_parser!(u8).range(1..=6),
+        require_equals(true)
+    )]
+    max_heading_level: Option<u8>,
+
     /// Include footnotes.
     #[clap(
         long,
@@ -283,6 +667,15 @@ struct Args {
     )]
     tables: bool,
 
+    /// Which rows of tables to include.
+    #[clap(long, value_enum, default_value_t = Rows::All, require_equals(true))]
+    table_rows: Rows,
+
+    /// Exclude a table column, by its position counting from 1 or by the text
+    /// of its header cell. May be repeated.
+    #[clap(long, value_name = "COLUMN", require_equals(true))]
+    exclude_column: Vec<ColumnMatcher>,
+
     /// Include inline code.
     #[clap(
         long,
@@ -305,6 +698,24 @@ struct Args {
     )]
     block_code: bool,
 
+    /// Count fenced code blocks in this language (e.g. `text`) like prose,
+    /// even without `--block-code`. May be repeated.
+    #[clap(long, value_name = "LANGUAGE", require_equals(true))]
+    code_language: Vec<String>,
+
+    /// Count fenced code blocks in `markdown` or `md` as Markdown, rather than
+    /// as flat text. They still only count with `--block-code` or
+    /// `--code-language=markdown`.
+    #[clap(
+        long,
+        default_value = "false",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    markdown_code_blocks: bool,
+
     /// Include block HTML.
     #[clap(
         long,
@@ -315,6 +726,181 @@ struct Args {
         action = ArgAction::Set
     )]
     block_html: bool,
+
+    /// Include the alt text of Markdown images.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    image_text: bool,
+
+    /// Include the titles of links and images, and the `alt` and `title`
+    /// attributes of HTML `<img>` elements.
+    #[clap(
+        long,
+        default_value = "false",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    attributes: bool,
+
+    /// Include the terms being defined in definition lists.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    definition_titles: bool,
+
+    /// Include the definitions in definition lists.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    definitions: bool,
+
+    /// Include items in ordered (numbered) lists.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    ordered_lists: bool,
+
+    /// Include items in unordered (bulleted) lists.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    unordered_lists: bool,
+
+    /// Which task list items to include.
+    #[clap(long, value_enum, default_value_t = Tasks::All, require_equals(true))]
+    tasks: Tasks,
+
+    /// Only count list items nested at most this many lists deep: 1 counts only
+    /// the items in top-level lists.
+    #[clap(long, value_name = "DEPTH", require_equals(true))]
+    max_list_depth: Option<usize>,
+
+    /// Include struck-through text, `~~like this~~`. Pass `--strikethrough=false`
+    /// to leave out pending deletions.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    strikethrough: bool,
+
+    /// Include superscript text, `^like this^`.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    superscript: bool,
+
+    /// Include subscript text, `~like this~`.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    subscript: bool,
+
+    /// Include the text of wikilinks like `[[Page|label]]`. The target page is
+    /// never counted.
+    #[clap(
+        long,
+        default_value = "true",
+        default_missing_value = "true",
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set
+    )]
+    wikilinks: bool,
+
+    /// How to count inline and display math.
+    #[clap(long, value_enum, default_value_t = Math::Ignore, require_equals(true))]
+    math: Math,
+
+    /// How to count autolinks like `<https://example.com>` and bare URLs.
+    #[clap(long, value_enum, default_value_t = Urls::Word, require_equals(true))]
+    urls: Urls,
+
+    /// Which flavor of Markdown to parse documents as.
+    #[clap(long, value_enum, default_value_t = MarkdownDialect::All, require_equals(true))]
+    dialect: MarkdownDialect,
+
+    /// Turn on a Markdown extension which the dialect does not include. May be
+    /// repeated.
+    #[clap(long, value_enum, value_name = "EXTENSION", require_equals(true))]
+    enable: Vec<Extension>,
+
+    /// Turn off a Markdown extension which the dialect includes. May be
+    /// repeated.
+    #[clap(long, value_enum, value_name = "EXTENSION", require_equals(true))]
+    disable: Vec<Extension>,
+
+    /// Always include content matching a selector over the Markdown structure,
+    /// like `heading[level=1]` or `footnote blockquote`, whatever the other
+    /// options say. May be repeated.
+    #[clap(long, value_name = "SELECTOR", require_equals(true))]
+    include: Vec<Selector>,
+
+    /// Exclude content matching a selector over the Markdown structure, like
+    /// `blockquote > blockquote`, `table thead`, or `list list`. May be
+    /// repeated.
+    #[clap(long, value_name = "SELECTOR", require_equals(true))]
+    exclude: Vec<Selector>,
+
+    /// Exclude HTML elements matching a tag name (`aside`), a class
+    /// (`.nocount`), or an attribute (`[data-nocount]` or `[data-count=no]`).
+    /// May be repeated.
+    #[clap(long, value_name = "MATCHER", require_equals(true))]
+    exclude_html: Vec<HtmlMatcher>,
+
+    /// Exclude every section whose heading matches, up to the next heading of
+    /// the same or a higher level: heading text (`References`), a regex
+    /// (`/^Appendix/`), either optionally with a level (`## References`). May be
+    /// repeated.
+    #[clap(long, value_name = "HEADING", require_equals(true))]
+    exclude_section: Vec<HeadingMatcher>,
+
+    /// Count only the section under a heading (`## Abstract`), or at the end of
+    /// a path of headings (`Book > Part 2 > Chapter 3`), including its
+    /// subsections.
+    #[clap(long, value_name = "SELECTOR", require_equals(true))]
+    section: Option<SectionSelector>,
 }
 
 #[derive(clap::Args, Debug, PartialEq, Clone)]
@@ -399,7 +985,7 @@ fn to_input_buffers(paths: &[PathBuf]) -> Result<Input, Error> {
                 })
         })
         .collect::<Result<Vec<_>, Error>>()
-        .map(|inputs| Input::Files(inputs))
+        .map(Input::Files)
 }
 
 fn output_buffer(dest_cfg: &DestCfg) -> Result<Output, Error> {
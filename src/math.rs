@@ -0,0 +1,85 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How inline and display math are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MathMode {
+    /// Math is not counted at all.
+    #[default]
+    Ignore,
+    /// Each math expression counts as a single word.
+    Expression,
+    /// Only the words inside text-mode commands like `\text{…}` and
+    /// `\mathrm{…}` count, rather than each expression as a whole.
+    TextOnly,
+}
+
+/// TeX commands whose argument is prose rather than math.
+const TEXT_COMMANDS: &[&str] = &[
+    "text",
+    "textrm",
+    "textit",
+    "textbf",
+    "textsf",
+    "texttt",
+    "textnormal",
+    "mathrm",
+    "mbox",
+];
+
+/// Count the words in a math expression which appear as the arguments to
+/// text-mode commands like `\text{…}` or `\mathrm{…}`; everything else in the
+/// expression is ignored.
+pub(crate) fn text_words(expression: &str) -> u64 {
+    let mut words = 0;
+    let mut rest = expression;
+    while let Some(start) = rest.find('\\') {
+        let after_slash = &rest[start + 1..];
+        let name_len = after_slash
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after_slash.len());
+        let name = &after_slash[..name_len];
+        rest = &after_slash[name_len..];
+
+        // A backslash followed by a non-letter is an escape like `\{` or `\\`;
+        // skip the escaped character so it cannot open or close an argument.
+        if name.is_empty() {
+            let mut chars = rest.chars();
+            chars.next();
+            rest = chars.as_str();
+            continue;
+        }
+
+        if !TEXT_COMMANDS.contains(&name) {
+            continue;
+        }
+
+        let trimmed = rest.trim_start();
+        if let Some(argument) = trimmed.strip_prefix('{') {
+            let (content, remainder) = split_group(argument);
+            words += content.unicode_words().count() as u64;
+            rest = remainder;
+        }
+    }
+
+    words
+}
+
+/// Given the text just after an opening `{`, split it into the contents of the
+/// group and whatever follows its matching `}`. An unbalanced group extends to
+/// the end of the text.
+fn split_group(text: &str) -> (&str, &str) {
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' if depth == 0 => return (&text[..index], &text[index + 1..]),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    (text, "")
+}
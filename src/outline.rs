@@ -0,0 +1,80 @@
+use pulldown_cmark::HeadingLevel;
+
+/// A heading and everything under it, up to the next heading of the same or a
+/// higher level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub level: HeadingLevel,
+    /// The plain text of the heading, without any Markdown syntax.
+    pub title: String,
+    /// The words counted in this section itself (including its heading, if
+    /// headings are counted), but not in any of its subsections.
+    pub words: u64,
+    pub subsections: Vec<Section>,
+}
+
+impl Section {
+    /// The words counted in this section and all of its subsections.
+    pub fn total(&self) -> u64 {
+        self.words + self.subsections.iter().map(Section::total).sum::<u64>()
+    }
+}
+
+/// Builds up the tree of [`Section`]s as the parser reports headings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Outline {
+    pub(crate) preamble: u64,
+    pub(crate) sections: Vec<Section>,
+    /// The sections which have not yet been closed by a later heading, from
+    /// outermost to innermost.
+    open: Vec<Section>,
+}
+
+impl Outline {
+    pub(crate) fn open(&mut self, level: HeadingLevel) {
+        while self
+            .open
+            .last()
+            .is_some_and(|section| section.level >= level)
+        {
+            self.close_innermost();
+        }
+
+        self.open.push(Section {
+            level,
+            title: String::new(),
+            words: 0,
+            subsections: Vec::new(),
+        });
+    }
+
+    pub(crate) fn extend_title(&mut self, text: &str) {
+        if let Some(section) = self.open.last_mut() {
+            section.title.push_str(text);
+        }
+    }
+
+    pub(crate) fn add_words(&mut self, words: u64) {
+        match self.open.last_mut() {
+            Some(section) => section.words += words,
+            None => self.preamble += words,
+        }
+    }
+
+    pub(crate) fn finish(&mut self) {
+        while !self.open.is_empty() {
+            self.close_innermost();
+        }
+    }
+
+    fn close_innermost(&mut self) {
+        let Some(section) = self.open.pop() else {
+            return;
+        };
+
+        match self.open.last_mut() {
+            Some(parent) => parent.subsections.push(section),
+            None => self.sections.push(section),
+        }
+    }
+}
@@ -0,0 +1,561 @@
+use std::collections::BTreeMap;
+
+use pulldown_cmark::HeadingLevel;
+
+use crate::{outline::Outline, Directive, DirectiveTally, Options, Section, TextKind};
+
+/// The result of counting a document, broken down by the kind of content the
+/// words came from.
+///
+/// The [`total`](CountReport::total) is the same number [`count_with_options`]
+/// returns. The per-[`Category`] tallies let you see where those words came
+/// from, and how many words were left out (and why).
+///
+/// Categories overlap: a word in a table inside a footnote is tallied under
+/// both [`Category::Tables`] and [`Category::Footnotes`]. Words which are not
+/// inside any category (ordinary paragraphs, plain emphasis, etc.) are only
+/// reflected in the totals. Excluded words are only tallied under the
+/// categories which excluded them: a word in a blockquote inside a footnote,
+/// with blockquotes excluded, is excluded as a blockquote but not as a
+/// footnote. Words excluded by the other rules in a [`Config`](crate::Config)
+/// are tallied by [`Rule`] instead; see
+/// [`excluded_by`](CountReport::excluded_by).
+///
+/// The report also includes an outline of the document: the words counted in
+/// each of its [`sections`](CountReport::sections).
+///
+/// [`count_with_options`]: crate::count_with_options
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CountReport {
+    total: u64,
+    excluded: u64,
+    categories: BTreeMap<Category, Tally>,
+    heading_levels: BTreeMap<HeadingLevel, Tally>,
+    quote_depths: BTreeMap<usize, Tally>,
+    code_languages: Vec<(String, Tally)>,
+    outline: Outline,
+    directives: Vec<DirectiveTally>,
+    rules: BTreeMap<Rule, u64>,
+    wikilinks: Vec<String>,
+}
+
+impl CountReport {
+    /// The number of words counted.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of words which were present in the document but excluded by
+    /// the options used to count it.
+    pub fn excluded(&self) -> u64 {
+        self.excluded
+    }
+
+    /// The words included and excluded for a given category.
+    pub fn tally(&self, category: Category) -> Tally {
+        self.categories.get(&category).copied().unwrap_or_default()
+    }
+
+    /// Every category which had any words counted in it, or excluded because
+    /// of it.
+    pub fn categories(&self) -> impl Iterator<Item = (Category, Tally)> + '_ {
+        self.categories
+            .iter()
+            .map(|(category, tally)| (*category, *tally))
+    }
+
+    /// The words included and excluded in headings of a given level.
+    pub fn heading_level(&self, level: HeadingLevel) -> Tally {
+        self.heading_levels.get(&level).copied().unwrap_or_default()
+    }
+
+    /// Every heading level which had any words in it, included or excluded.
+    pub fn heading_levels(&self) -> impl Iterator<Item = (HeadingLevel, Tally)> + '_ {
+        self.heading_levels
+            .iter()
+            .map(|(level, tally)| (*level, *tally))
+    }
+
+    /// The words included and excluded in blockquotes nested to a given
+    /// depth: 1 for a blockquote, 2 for a blockquote inside that, and so on.
+    pub fn quote_depth(&self, depth: usize) -> Tally {
+        self.quote_depths.get(&depth).copied().unwrap_or_default()
+    }
+
+    /// Every blockquote depth which had any words in it, included or excluded.
+    pub fn quote_depths(&self) -> impl Iterator<Item = (usize, Tally)> + '_ {
+        self.quote_depths
+            .iter()
+            .map(|(depth, tally)| (*depth, *tally))
+    }
+
+    /// The words included and excluded in fenced code blocks in a given
+    /// language, like `rust`.
+    pub fn code_language(&self, language: &str) -> Tally {
+        self.code_languages
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(language))
+            .map(|(_, tally)| *tally)
+            .unwrap_or_default()
+    }
+
+    /// Every language of fenced code block in the document, in the order they
+    /// first appear. Blocks with no language are not listed.
+    pub fn code_languages(&self) -> impl Iterator<Item = (&str, Tally)> + '_ {
+        self.code_languages
+            .iter()
+            .map(|(name, tally)| (name.as_str(), *tally))
+    }
+
+    /// The top-level sections of the document, each with its subsections.
+    pub fn sections(&self) -> &[Section] {
+        &self.outline.sections
+    }
+
+    /// The words counted before the first heading in the document.
+    pub fn preamble(&self) -> u64 {
+        self.outline.preamble
+    }
+
+    /// Each directive in the document which excludes words (i.e. every one
+    /// except `on`), in the order they appear.
+    pub fn directives(&self) -> &[DirectiveTally] {
+        &self.directives
+    }
+
+    /// The number of words a rule excluded from the count. Words which were
+    /// excluded for other reasons as well are included here.
+    pub fn excluded_by(&self, rule: Rule) -> u64 {
+        self.rules.get(&rule).copied().unwrap_or_default()
+    }
+
+    /// Every rule which excluded any words, with how many it excluded.
+    pub fn rules(&self) -> impl Iterator<Item = (Rule, u64)> + '_ {
+        self.rules.iter().map(|(rule, words)| (*rule, *words))
+    }
+
+    /// The target of each wikilink in the document, like `Page` for both
+    /// `[[Page]]` and `[[Page|label]]`, in the order they appear. Targets are
+    /// never counted, and are listed whether or not the link text was.
+    pub fn wikilinks(&self) -> &[String] {
+        &self.wikilinks
+    }
+
+    /// Note a directive, returning the index to use for any words it excludes.
+    pub(crate) fn add_directive(&mut self, directive: Directive) -> usize {
+        self.directives.push(DirectiveTally {
+            directive,
+            excluded: 0,
+        });
+        self.directives.len() - 1
+    }
+
+    /// Note a code block language, returning the index to use for any words in
+    /// the block.
+    pub(crate) fn add_code_language(&mut self, language: String) -> usize {
+        match self
+            .code_languages
+            .iter()
+            .position(|(name, _)| *name == language)
+        {
+            Some(index) => index,
+            None => {
+                self.code_languages.push((language, Tally::default()));
+                self.code_languages.len() - 1
+            }
+        }
+    }
+
+    pub(crate) fn add_wikilink(&mut self, target: &str) {
+        self.wikilinks.push(target.to_string());
+    }
+
+    pub(crate) fn open_section(&mut self, level: HeadingLevel) {
+        self.outline.open(level);
+    }
+
+    pub(crate) fn extend_section_title(&mut self, text: &str) {
+        self.outline.extend_title(text);
+    }
+
+    pub(crate) fn finish(&mut self) {
+        self.outline.finish();
+    }
+
+    pub(crate) fn record(&mut self, attribution: Attribution, words: u64) {
+        if words == 0 {
+            return;
+        }
+
+        let Attribution {
+            categories,
+            included,
+            excluded_as,
+            excluded_by,
+            directive,
+            heading,
+            quote_depth,
+            code_language,
+        } = attribution;
+
+        if included {
+            self.total += words;
+            self.outline.add_words(words);
+        } else {
+            self.excluded += words;
+        }
+
+        if let Some(tally) = directive.and_then(|index| self.directives.get_mut(index)) {
+            tally.excluded += words;
+        }
+
+        for category in categories.iter() {
+            if included || excluded_as.contains(category) {
+                self.categories
+                    .entry(category)
+                    .or_default()
+                    .add(included, words);
+            }
+        }
+
+        for rule in excluded_by.iter() {
+            *self.rules.entry(rule).or_default() += words;
+        }
+
+        if let Some(level) = heading {
+            self.heading_levels
+                .entry(level)
+                .or_default()
+                .add(included, words);
+        }
+
+        if quote_depth > 0 {
+            self.quote_depths
+                .entry(quote_depth)
+                .or_default()
+                .add(included, words);
+        }
+
+        if let Some((_, tally)) = code_language.and_then(|index| self.code_languages.get_mut(index))
+        {
+            tally.add(included, words);
+        }
+    }
+}
+
+/// Where some words came from, and whether (and why) they are counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Attribution {
+    pub(crate) categories: Categories,
+    pub(crate) included: bool,
+    /// The categories whose options excluded the words, if any.
+    pub(crate) excluded_as: Categories,
+    /// The other rules which excluded the words, if any.
+    pub(crate) excluded_by: Rules,
+    /// The index of the directive which excluded the words, if any.
+    pub(crate) directive: Option<usize>,
+    /// The level of the heading the words are in, if any.
+    pub(crate) heading: Option<HeadingLevel>,
+    /// How many blockquotes deep the words are, if they are in one at all.
+    pub(crate) quote_depth: usize,
+    /// The index of the language of the code block the words are in, if any.
+    pub(crate) code_language: Option<usize>,
+}
+
+/// How many words in a [`Category`] were included in or excluded from a count.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub included: u64,
+    pub excluded: u64,
+}
+
+impl Tally {
+    fn add(&mut self, included: bool, words: u64) {
+        if included {
+            self.included += words;
+        } else {
+            self.excluded += words;
+        }
+    }
+}
+
+/// The kinds of content which can be included in or excluded from a count.
+///
+/// Most categories correspond to one of the [`Options`] flags; see
+/// [`Category::option`]. Math, URLs, and the content which has its own on/off
+/// switch (image text, definition lists, and so on) are controlled by the
+/// fields of [`Config`](crate::Config) instead, e.g.
+/// [`Config::math`](crate::Config::math), and link text is always counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Headings,
+    Tables,
+    Footnotes,
+    Blockquotes,
+    InlineCode,
+    BlockCode,
+    BlockHtml,
+    Metadata,
+    InlineMath,
+    DisplayMath,
+    Urls,
+    /// The text of links (but not their URLs). This is always counted, and is
+    /// only broken out in the report.
+    LinkText,
+    /// The alt text of Markdown images.
+    ImageText,
+    /// The titles of links and images, like `[text](url "Title")`, and the
+    /// `alt` and `title` attributes of HTML `<img>` elements.
+    Attributes,
+    DefinitionTitles,
+    Definitions,
+    OrderedLists,
+    UnorderedLists,
+    CheckedTasks,
+    UncheckedTasks,
+    Alerts,
+    Strikethrough,
+    Superscript,
+    Subscript,
+    /// The text of wikilinks, which is not included in
+    /// [`Category::LinkText`].
+    Wikilinks,
+}
+
+impl Category {
+    const ALL: [Category; 25] = [
+        Category::Headings,
+        Category::Tables,
+        Category::Footnotes,
+        Category::Blockquotes,
+        Category::InlineCode,
+        Category::BlockCode,
+        Category::BlockHtml,
+        Category::Metadata,
+        Category::InlineMath,
+        Category::DisplayMath,
+        Category::Urls,
+        Category::LinkText,
+        Category::ImageText,
+        Category::Attributes,
+        Category::DefinitionTitles,
+        Category::Definitions,
+        Category::OrderedLists,
+        Category::UnorderedLists,
+        Category::CheckedTasks,
+        Category::UncheckedTasks,
+        Category::Alerts,
+        Category::Strikethrough,
+        Category::Superscript,
+        Category::Subscript,
+        Category::Wikilinks,
+    ];
+
+    /// The [`Options`] flag which controls whether this category is counted, or
+    /// no flags at all for categories which are always counted or which the
+    /// [`Config`](crate::Config) controls instead.
+    pub fn option(self) -> Options {
+        match self {
+            Category::Headings => Options::IncludeHeadings,
+            Category::Tables => Options::IncludeTables,
+            Category::Footnotes => Options::IncludeFootnotes,
+            Category::Blockquotes => Options::IncludeBlockquotes,
+            Category::InlineCode => Options::IncludeInlineCode,
+            Category::BlockCode => Options::IncludeBlockCode,
+            Category::BlockHtml => Options::IncludeBlockHtml,
+            Category::Metadata => Options::IncludeMetadata,
+            Category::Alerts => Options::IncludeAlerts,
+            Category::InlineMath
+            | Category::DisplayMath
+            | Category::Urls
+            | Category::LinkText
+            | Category::ImageText
+            | Category::Attributes
+            | Category::DefinitionTitles
+            | Category::Definitions
+            | Category::OrderedLists
+            | Category::UnorderedLists
+            | Category::CheckedTasks
+            | Category::UncheckedTasks
+            | Category::Strikethrough
+            | Category::Superscript
+            | Category::Subscript
+            | Category::Wikilinks => Options::empty(),
+        }
+    }
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Category::Headings => "headings",
+            Category::Tables => "tables",
+            Category::Footnotes => "footnotes",
+            Category::Blockquotes => "blockquotes",
+            Category::InlineCode => "inline code",
+            Category::BlockCode => "code blocks",
+            Category::BlockHtml => "block HTML",
+            Category::Metadata => "metadata",
+            Category::InlineMath => "inline math",
+            Category::DisplayMath => "display math",
+            Category::Urls => "URLs",
+            Category::LinkText => "link text",
+            Category::ImageText => "image text",
+            Category::Attributes => "attributes",
+            Category::DefinitionTitles => "definition titles",
+            Category::Definitions => "definitions",
+            Category::OrderedLists => "ordered lists",
+            Category::UnorderedLists => "unordered lists",
+            Category::CheckedTasks => "checked tasks",
+            Category::UncheckedTasks => "unchecked tasks",
+            Category::Alerts => "alerts",
+            Category::Strikethrough => "strikethrough",
+            Category::Superscript => "superscript",
+            Category::Subscript => "subscript",
+            Category::Wikilinks => "wikilinks",
+        })
+    }
+}
+
+/// A set of [`Category`]s: the kinds of content some text is inside of.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Categories(u32);
+
+impl Categories {
+    pub(crate) fn with(self, category: Category) -> Categories {
+        Categories(self.0 | 1 << category as u32)
+    }
+
+    pub(crate) fn without(self, category: Category) -> Categories {
+        Categories(self.0 & !(1 << category as u32))
+    }
+
+    pub(crate) fn contains(self, category: Category) -> bool {
+        self.0 & 1 << category as u32 != 0
+    }
+
+    pub(crate) fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Add the category for a kind of text, if it has one.
+    pub(crate) fn with_kind(self, kind: TextKind) -> Categories {
+        match kind {
+            TextKind::Text => self,
+            TextKind::InlineCode => self.with(Category::InlineCode),
+            TextKind::InlineMath => self.with(Category::InlineMath),
+            TextKind::DisplayMath => self.with(Category::DisplayMath),
+            TextKind::Url => self.with(Category::Urls),
+            TextKind::Attribute => self.with(Category::Attributes),
+        }
+    }
+
+    pub(crate) fn iter(self) -> impl Iterator<Item = Category> {
+        Category::ALL
+            .into_iter()
+            .filter(move |category| self.contains(*category))
+    }
+
+    /// Whether the options include every one of these categories.
+    pub(crate) fn allowed_by(self, options: &Options) -> bool {
+        self.iter()
+            .all(|category| options.contains(category.option()))
+    }
+}
+
+impl FromIterator<Category> for Categories {
+    fn from_iter<I: IntoIterator<Item = Category>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Categories::default(), Categories::with)
+    }
+}
+
+/// A rule in the [`Config`](crate::Config), other than the options for each
+/// [`Category`], which can leave words out of a count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rule {
+    /// A custom [`CountFilter`](crate::CountFilter), for some reason other than
+    /// the categories the words are in.
+    Filter,
+    /// [`Config::exclude`](crate::Config::exclude).
+    Selector,
+    /// [`Config::exclude_html`](crate::Config::exclude_html).
+    Html,
+    /// [`Config::max_list_depth`](crate::Config::max_list_depth).
+    ListDepth,
+    /// [`Config::max_blockquote_depth`](crate::Config::max_blockquote_depth).
+    BlockquoteDepth,
+    /// [`Config::max_heading_level`](crate::Config::max_heading_level).
+    HeadingLevel,
+    /// [`Config::exclude_sections`](crate::Config::exclude_sections) and
+    /// [`Config::section`](crate::Config::section).
+    Section,
+    /// [`Config::exclude_alerts`](crate::Config::exclude_alerts).
+    Alert,
+    /// [`Config::table_rows`](crate::Config::table_rows).
+    TableRows,
+    /// [`Config::exclude_columns`](crate::Config::exclude_columns).
+    Column,
+}
+
+impl Rule {
+    const ALL: [Rule; 10] = [
+        Rule::Filter,
+        Rule::Selector,
+        Rule::Html,
+        Rule::ListDepth,
+        Rule::BlockquoteDepth,
+        Rule::HeadingLevel,
+        Rule::Section,
+        Rule::Alert,
+        Rule::TableRows,
+        Rule::Column,
+    ];
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Rule::Filter => "the filter",
+            Rule::Selector => "selectors",
+            Rule::Html => "HTML rules",
+            Rule::ListDepth => "list depth",
+            Rule::BlockquoteDepth => "blockquote depth",
+            Rule::HeadingLevel => "heading level",
+            Rule::Section => "section rules",
+            Rule::Alert => "alert kind",
+            Rule::TableRows => "table rows",
+            Rule::Column => "table columns",
+        })
+    }
+}
+
+/// A set of [`Rule`]s: the reasons some words were excluded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Rules(u16);
+
+impl Rules {
+    pub(crate) fn with(self, rule: Rule) -> Rules {
+        Rules(self.0 | 1 << rule as u16)
+    }
+
+    pub(crate) fn contains(self, rule: Rule) -> bool {
+        self.0 & 1 << rule as u16 != 0
+    }
+
+    pub(crate) fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub(crate) fn iter(self) -> impl Iterator<Item = Rule> {
+        Rule::ALL
+            .into_iter()
+            .filter(move |rule| self.contains(*rule))
+    }
+}
+
+impl FromIterator<Rule> for Rules {
+    fn from_iter<I: IntoIterator<Item = Rule>>(iter: I) -> Self {
+        iter.into_iter().fold(Rules::default(), Rules::with)
+    }
+}
@@ -0,0 +1,154 @@
+use pulldown_cmark::HeadingLevel;
+use regex::Regex;
+
+/// A rule for headings, written as the text of the heading, or as a regular
+/// expression between slashes. Either may start with `#`s to match only
+/// headings of that level:
+///
+/// - `References` matches any heading whose text is exactly "References".
+/// - `## References` matches only level 2 headings with that text.
+/// - `/^Appendix/` matches any heading whose text matches the regex.
+/// - `### /(?i)notes?/` matches only level 3 headings whose text matches it.
+///
+/// The text of a heading is its plain text, without any Markdown syntax or
+/// leading and trailing whitespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeadingMatcher {
+    pub level: Option<HeadingLevel>,
+    pub text: TextMatcher,
+}
+
+/// How a [`HeadingMatcher`] matches the text of a heading.
+#[derive(Clone, Debug)]
+pub enum TextMatcher {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl HeadingMatcher {
+    pub(crate) fn matches(&self, level: HeadingLevel, title: &str) -> bool {
+        let title = title.trim();
+        self.level.is_none_or(|expected| expected == level)
+            && match &self.text {
+                TextMatcher::Exact(text) => text == title,
+                TextMatcher::Regex(regex) => regex.is_match(title),
+            }
+    }
+}
+
+impl PartialEq for TextMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TextMatcher::Exact(a), TextMatcher::Exact(b)) => a == b,
+            (TextMatcher::Regex(a), TextMatcher::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TextMatcher {}
+
+impl std::str::FromStr for HeadingMatcher {
+    type Err = ParseHeadingMatcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let error = |regex| ParseHeadingMatcherError {
+            matcher: s.to_string(),
+            regex,
+        };
+
+        let (level, text) = match s.find(|c| c != '#') {
+            Some(0) => (None, s),
+            Some(hashes) if s[hashes..].starts_with(char::is_whitespace) => {
+                let level = HeadingLevel::try_from(hashes).map_err(|_| error(None))?;
+                (Some(level), s[hashes..].trim_start())
+            }
+            _ => return Err(error(None)),
+        };
+
+        let text = match text.strip_prefix('/').and_then(|t| t.strip_suffix('/')) {
+            Some(pattern) => {
+                TextMatcher::Regex(Regex::new(pattern).map_err(|err| error(Some(err)))?)
+            }
+            None if text.is_empty() => return Err(error(None)),
+            None => TextMatcher::Exact(text.to_string()),
+        };
+
+        Ok(HeadingMatcher { level, text })
+    }
+}
+
+/// A rule for picking out a section of a document by its heading, or by the
+/// path of headings leading to it, separated by ` > `:
+///
+/// - `## Abstract` matches the level 2 section titled "Abstract".
+/// - `Book > Part 2 > Chapter 3` matches a section titled "Chapter 3" inside a
+///   section titled "Part 2" inside one titled "Book". The sections in the path
+///   do not have to be immediate subsections of each other.
+///
+/// Each step in the path is a [`HeadingMatcher`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionSelector {
+    pub path: Vec<HeadingMatcher>,
+}
+
+impl SectionSelector {
+    /// Whether the innermost of the given headings (from outermost to
+    /// innermost) starts a section the selector matches.
+    pub(crate) fn matches(&self, headings: &[(HeadingLevel, String)]) -> bool {
+        let Some(((level, title), ancestors)) = headings.split_last() else {
+            return false;
+        };
+        let Some((last, path)) = self.path.split_last() else {
+            return false;
+        };
+
+        let mut ancestors = ancestors.iter().rev();
+        last.matches(*level, title)
+            && path.iter().rev().all(|matcher| {
+                ancestors
+                    .by_ref()
+                    .any(|(level, title)| matcher.matches(*level, title))
+            })
+    }
+}
+
+impl std::str::FromStr for SectionSelector {
+    type Err = ParseHeadingMatcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = s
+            .split(" > ")
+            .map(str::parse)
+            .collect::<Result<Vec<HeadingMatcher>, _>>()?;
+        Ok(SectionSelector { path })
+    }
+}
+
+/// The error when a string is not a valid [`HeadingMatcher`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseHeadingMatcherError {
+    matcher: String,
+    regex: Option<regex::Error>,
+}
+
+impl std::fmt::Display for ParseHeadingMatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.regex {
+            Some(err) => write!(
+                f,
+                "invalid regex in heading matcher '{}': {err}",
+                self.matcher
+            ),
+            None => write!(
+                f,
+                "invalid heading matcher '{}': expected heading text like `References` or a \
+                regex like `/^Appendix/`, optionally after a level like `## References`",
+                self.matcher
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseHeadingMatcherError {}
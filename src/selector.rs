@@ -0,0 +1,377 @@
+use pulldown_cmark::{BlockQuoteKind, CodeBlockKind, Tag};
+
+/// A rule for picking out content by the Markdown structure around it, written
+/// like a (very) simple CSS selector:
+///
+/// - `blockquote` matches everything inside a blockquote.
+/// - `footnote blockquote` matches blockquotes anywhere inside a footnote.
+/// - `blockquote > blockquote` matches blockquotes directly inside another.
+/// - `heading[level>=3]` matches headings of level 3 and below.
+///
+/// The elements are `blockquote`, `alert`, `list`, `ol`, `ul`, `item`,
+/// `table`, `thead`, `row`, `cell`, `heading`, `paragraph`, `code` (code
+/// blocks), `footnote`, `html` (block HTML), `metadata`, `emphasis`, `strong`,
+/// `strikethrough`, `superscript`, `subscript`, `link`, `image`, `dl`, `dt`,
+/// `dd`, and `*` for any element. Elements can be narrowed down by their
+/// `level` (headings), `lang` (code blocks), or `kind` (alerts), using `=`,
+/// `!=`, `<`, `<=`, `>`, or `>=`: e.g. `code[lang=rust]` or `alert[kind=note]`.
+/// A `level` is a number, and a `kind` is `note`, `tip`, `important`,
+/// `warning`, or `caution`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Step {
+    /// How this step relates to the one before it.
+    combinator: Combinator,
+    element: Element,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Element {
+    Any,
+    Blockquote,
+    Alert,
+    List,
+    OrderedList,
+    UnorderedList,
+    Item,
+    Table,
+    TableHead,
+    TableRow,
+    TableCell,
+    Heading,
+    Paragraph,
+    Code,
+    Footnote,
+    Html,
+    Metadata,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Superscript,
+    Subscript,
+    Link,
+    Image,
+    DefinitionList,
+    DefinitionTitle,
+    Definition,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Condition {
+    attribute: Attribute,
+    operator: Operator,
+    value: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Attribute {
+    Level,
+    Lang,
+    Kind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Selector {
+    /// Whether text inside the given tags (from outermost to innermost) is
+    /// matched by the selector, i.e. whether any of the tags matches it.
+    pub(crate) fn matches(&self, tags: &[Tag<'_>]) -> bool {
+        (0..tags.len()).any(|index| self.matches_at(self.steps.len() - 1, tags, index))
+    }
+
+    /// Whether the step at `step` matches the tag at `index`, and all the steps
+    /// before it match the tags around it.
+    fn matches_at(&self, step: usize, tags: &[Tag<'_>], index: usize) -> bool {
+        let current = &self.steps[step];
+        if !current.matches(&tags[index]) {
+            return false;
+        }
+
+        if step == 0 {
+            return true;
+        }
+
+        match current.combinator {
+            Combinator::Child => index > 0 && self.matches_at(step - 1, tags, index - 1),
+            Combinator::Descendant => (0..index)
+                .rev()
+                .any(|parent| self.matches_at(step - 1, tags, parent)),
+        }
+    }
+}
+
+impl Step {
+    fn matches(&self, tag: &Tag<'_>) -> bool {
+        self.element.matches(tag)
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(tag))
+    }
+}
+
+impl Element {
+    fn from_name(name: &str) -> Option<Element> {
+        Some(match name {
+            "*" => Element::Any,
+            "blockquote" => Element::Blockquote,
+            "alert" => Element::Alert,
+            "list" => Element::List,
+            "ol" => Element::OrderedList,
+            "ul" => Element::UnorderedList,
+            "item" => Element::Item,
+            "table" => Element::Table,
+            "thead" => Element::TableHead,
+            "row" => Element::TableRow,
+            "cell" => Element::TableCell,
+            "heading" => Element::Heading,
+            "paragraph" => Element::Paragraph,
+            "code" => Element::Code,
+            "footnote" => Element::Footnote,
+            "html" => Element::Html,
+            "metadata" => Element::Metadata,
+            "emphasis" => Element::Emphasis,
+            "strong" => Element::Strong,
+            "strikethrough" => Element::Strikethrough,
+            "superscript" => Element::Superscript,
+            "subscript" => Element::Subscript,
+            "link" => Element::Link,
+            "image" => Element::Image,
+            "dl" => Element::DefinitionList,
+            "dt" => Element::DefinitionTitle,
+            "dd" => Element::Definition,
+            _ => return None,
+        })
+    }
+
+    fn matches(self, tag: &Tag<'_>) -> bool {
+        match self {
+            Element::Any => true,
+            Element::Blockquote => matches!(tag, Tag::BlockQuote(None)),
+            Element::Alert => matches!(tag, Tag::BlockQuote(Some(_))),
+            Element::List => matches!(tag, Tag::List(_)),
+            Element::OrderedList => matches!(tag, Tag::List(Some(_))),
+            Element::UnorderedList => matches!(tag, Tag::List(None)),
+            Element::Item => matches!(tag, Tag::Item),
+            Element::Table => matches!(tag, Tag::Table(_)),
+            Element::TableHead => matches!(tag, Tag::TableHead),
+            Element::TableRow => matches!(tag, Tag::TableRow),
+            Element::TableCell => matches!(tag, Tag::TableCell),
+            Element::Heading => matches!(tag, Tag::Heading { .. }),
+            Element::Paragraph => matches!(tag, Tag::Paragraph),
+            Element::Code => matches!(tag, Tag::CodeBlock(_)),
+            Element::Footnote => matches!(tag, Tag::FootnoteDefinition(_)),
+            Element::Html => matches!(tag, Tag::HtmlBlock),
+            Element::Metadata => matches!(tag, Tag::MetadataBlock(_)),
+            Element::Emphasis => matches!(tag, Tag::Emphasis),
+            Element::Strong => matches!(tag, Tag::Strong),
+            Element::Strikethrough => matches!(tag, Tag::Strikethrough),
+            Element::Superscript => matches!(tag, Tag::Superscript),
+            Element::Subscript => matches!(tag, Tag::Subscript),
+            Element::Link => matches!(tag, Tag::Link { .. }),
+            Element::Image => matches!(tag, Tag::Image { .. }),
+            Element::DefinitionList => matches!(tag, Tag::DefinitionList),
+            Element::DefinitionTitle => matches!(tag, Tag::DefinitionListTitle),
+            Element::Definition => matches!(tag, Tag::DefinitionListDefinition),
+        }
+    }
+}
+
+impl Condition {
+    /// Whether the tag has the attribute, with a value which satisfies the
+    /// condition. Levels are compared as numbers, everything else as text.
+    fn matches(&self, tag: &Tag<'_>) -> bool {
+        let Some(actual) = self.attribute.of(tag) else {
+            return false;
+        };
+
+        let ordering = match self.attribute {
+            Attribute::Level => {
+                let (Ok(actual), Ok(expected)) =
+                    (actual.parse::<usize>(), self.value.parse::<usize>())
+                else {
+                    return false;
+                };
+                actual.cmp(&expected)
+            }
+            Attribute::Lang | Attribute::Kind => actual.as_str().cmp(self.value.as_str()),
+        };
+
+        match self.operator {
+            Operator::Eq => ordering.is_eq(),
+            Operator::Ne => ordering.is_ne(),
+            Operator::Lt => ordering.is_lt(),
+            Operator::Le => ordering.is_le(),
+            Operator::Gt => ordering.is_gt(),
+            Operator::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+impl Attribute {
+    fn of(self, tag: &Tag<'_>) -> Option<String> {
+        match (self, tag) {
+            (Attribute::Level, Tag::Heading { level, .. }) => Some((*level as usize).to_string()),
+            (Attribute::Lang, Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                crate::code_language(info)
+            }
+            (Attribute::Kind, Tag::BlockQuote(Some(kind))) => Some(
+                match kind {
+                    BlockQuoteKind::Note => "note",
+                    BlockQuoteKind::Tip => "tip",
+                    BlockQuoteKind::Important => "important",
+                    BlockQuoteKind::Warning => "warning",
+                    BlockQuoteKind::Caution => "caution",
+                }
+                .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Selector {
+    type Err = ParseSelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseSelectorError(s.trim().to_string());
+
+        let mut steps = Vec::new();
+        let mut rest = s.trim();
+        let mut combinator = Combinator::Descendant;
+        while !rest.is_empty() {
+            let (step, after) = parse_step(rest, combinator).ok_or_else(error)?;
+            steps.push(step);
+
+            let trimmed = after.trim_start();
+            combinator = match trimmed.strip_prefix('>') {
+                Some(after_child) => {
+                    rest = after_child.trim_start();
+                    if rest.is_empty() {
+                        return Err(error());
+                    }
+                    Combinator::Child
+                }
+                None if trimmed.len() < after.len() || trimmed.is_empty() => {
+                    rest = trimmed;
+                    Combinator::Descendant
+                }
+                None => return Err(error()),
+            };
+        }
+
+        if steps.is_empty() {
+            return Err(error());
+        }
+        Ok(Selector { steps })
+    }
+}
+
+/// Parse a single step like `heading[level>=3]` from the start of `s`,
+/// returning it and whatever is left after it.
+fn parse_step(s: &str, combinator: Combinator) -> Option<(Step, &str)> {
+    let end = s
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '[')
+        .unwrap_or(s.len());
+    let element = Element::from_name(&s[..end].to_ascii_lowercase())?;
+
+    let mut rest = &s[end..];
+    let mut conditions = Vec::new();
+    while let Some(after) = rest.strip_prefix('[') {
+        let (condition, after) = after.split_once(']')?;
+        conditions.push(parse_condition(condition)?);
+        rest = after;
+    }
+
+    let step = Step {
+        combinator,
+        element,
+        conditions,
+    };
+    Some((step, rest))
+}
+
+/// Parse the inside of a condition like `[level>=3]`.
+fn parse_condition(s: &str) -> Option<Condition> {
+    let start = s.find(['=', '!', '<', '>'])?;
+    let (operator, len) = match &s[start..] {
+        op if op.starts_with("!=") => (Operator::Ne, 2),
+        op if op.starts_with("<=") => (Operator::Le, 2),
+        op if op.starts_with(">=") => (Operator::Ge, 2),
+        op if op.starts_with('=') => (Operator::Eq, 1),
+        op if op.starts_with('<') => (Operator::Lt, 1),
+        op if op.starts_with('>') => (Operator::Gt, 1),
+        _ => return None,
+    };
+
+    let attribute = match s[..start].trim() {
+        "level" => Attribute::Level,
+        "lang" => Attribute::Lang,
+        "kind" => Attribute::Kind,
+        _ => return None,
+    };
+
+    let value = s[start + len..].trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+    if value.is_empty() {
+        return None;
+    }
+
+    // Catch typos here, rather than with a selector which never matches.
+    let valid = match attribute {
+        Attribute::Level => value.parse::<usize>().is_ok(),
+        Attribute::Lang => true,
+        Attribute::Kind => ["note", "tip", "important", "warning", "caution"]
+            .iter()
+            .any(|kind| kind.eq_ignore_ascii_case(value)),
+    };
+    if !valid {
+        return None;
+    }
+
+    Some(Condition {
+        attribute,
+        operator,
+        value: value.to_ascii_lowercase(),
+    })
+}
+
+/// The error when a string is not a valid [`Selector`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSelectorError(String);
+
+impl std::fmt::Display for ParseSelectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid selector '{}': expected Markdown elements like `blockquote`, \
+            optionally with conditions like `heading[level>=3]`, separated by spaces or `>`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseSelectorError {}
@@ -0,0 +1,91 @@
+use pulldown_cmark::{Event, Tag};
+
+/// Which rows of a table are counted, when tables are counted at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TableRows {
+    /// Every row.
+    #[default]
+    All,
+    /// Only the header row.
+    Header,
+    /// Only the rows after the header row.
+    Body,
+}
+
+/// A rule for table columns which should never be counted: either the position
+/// of the column, counting from 1 (like a spreadsheet), or the text of its
+/// header cell, ignoring case.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColumnMatcher {
+    Index(usize),
+    Header(String),
+}
+
+impl ColumnMatcher {
+    fn matches(&self, index: usize, header: &str) -> bool {
+        match self {
+            ColumnMatcher::Index(expected) => *expected == index,
+            ColumnMatcher::Header(expected) => expected.eq_ignore_ascii_case(header.trim()),
+        }
+    }
+}
+
+impl std::str::FromStr for ColumnMatcher {
+    type Err = ParseColumnMatcherError;
+
+    /// A number is the position of a column; anything else is header text.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.parse::<usize>() {
+            Ok(0) => Err(ParseColumnMatcherError(s.to_string())),
+            Ok(index) => Ok(ColumnMatcher::Index(index)),
+            Err(_) if s.is_empty() => Err(ParseColumnMatcherError(s.to_string())),
+            Err(_) => Ok(ColumnMatcher::Header(s.to_string())),
+        }
+    }
+}
+
+/// The error when a string is not a valid [`ColumnMatcher`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColumnMatcherError(String);
+
+impl std::fmt::Display for ParseColumnMatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid column '{}': expected a column number starting from 1, or the text of a \
+            header cell",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseColumnMatcherError {}
+
+/// The positions (counting from 1) of the columns any of the matchers match,
+/// given the events of a table's header row.
+pub(crate) fn excluded_columns(matchers: &[ColumnMatcher], header: &[Event<'_>]) -> Vec<usize> {
+    let mut headers = Vec::<String>::new();
+    for event in header {
+        match event {
+            Event::Start(Tag::TableCell) => headers.push(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(cell) = headers.last_mut() {
+                    cell.push_str(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    headers
+        .iter()
+        .zip(1..)
+        .filter(|(header, index)| {
+            matchers
+                .iter()
+                .any(|matcher| matcher.matches(*index, header))
+        })
+        .map(|(_, index)| index)
+        .collect()
+}
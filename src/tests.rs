@@ -1,5 +1,14 @@
 use super::*;
 
+/// Parse each of a list of matchers or selectors, for the rules in a `Config`.
+fn parse_all<T>(items: &[&str]) -> Vec<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Debug,
+{
+    items.iter().map(|item| item.parse().unwrap()).collect()
+}
+
 #[test]
 fn includes_basic_text() {
     let result = count("Hello, world!");
@@ -128,202 +137,1899 @@ fn handles_footnotes_with_continued_content() {
     assert_eq!(result, 13);
 }
 
-mod options {
-    use super::*;
+mod inline_markup {
+    use super::*;
+
+    #[test]
+    fn joins_words_split_by_emphasis() {
+        assert_eq!(count("That is un**believ**able."), 3);
+        assert_eq!(count("That is un*believ*able."), 3);
+        assert_eq!(count("That is un~~believ~~able."), 3);
+    }
+
+    #[test]
+    fn joins_words_split_by_escapes() {
+        assert_eq!(count(r"Call foo\_bar now."), 3);
+    }
+
+    #[test]
+    fn joins_words_split_by_smart_punctuation() {
+        assert_eq!(count("Don't stop, it's fine."), 4);
+    }
+
+    #[test]
+    fn joins_words_split_by_entities() {
+        assert_eq!(count("A caf&eacute; au lait."), 4);
+    }
+
+    #[test]
+    fn keeps_real_word_boundaries() {
+        assert_eq!(count("*One* **two** three"), 3);
+        assert_eq!(count("One.[^fn]Two\n\n[^fn]: Three"), 3);
+        assert_eq!(count("One  \ntwo"), 2);
+    }
+
+    #[test]
+    fn does_not_join_across_blocks() {
+        assert_eq!(count("# One\nTwo\n\n- three\n- four"), 4);
+    }
+
+    #[test]
+    fn joins_words_hyphenated_across_soft_breaks() {
+        assert_eq!(count("An extra-\nordinary result."), 3);
+    }
+
+    #[test]
+    fn does_not_join_dashes_across_soft_breaks() {
+        assert_eq!(count("Wait -\nwhat?"), 2);
+        assert_eq!(count("One\ntwo"), 2);
+    }
+
+    #[test]
+    fn counts_joined_words_by_where_they_start() {
+        let result = report_with_options("Some `code`words here.", Options::empty());
+        assert_eq!(result.total(), 2);
+        assert_eq!(result.tally(Category::InlineCode).excluded, 1);
+    }
+}
+
+mod html {
+    use super::*;
+
+    #[test]
+    fn handles_elements_spanning_lines() {
+        let result = count("<div\n  class=\"note\"\n  id='x'>Some words\nhere.</div>");
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn handles_unquoted_attributes() {
+        assert_eq!(count("<div class=note>Some words</div>"), 2);
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(count("<p>Fish &amp; chips&nbsp;today, caf&eacute;s</p>"), 4);
+    }
+
+    #[test]
+    fn handles_void_elements() {
+        assert_eq!(count("<p>Line one<br>line two<img src=x.png></p>"), 4);
+    }
+
+    #[test]
+    fn excludes_script_and_style() {
+        let result = count(
+            "<script>\nvar x = '<b>not prose</b>';\n</script>
+
+<style>
+p { color: red; }
+</style>
+
+Text.",
+        );
+        assert_eq!(result, 1);
+    }
+
+    #[test]
+    fn joins_words_split_by_phrasing_elements() {
+        assert_eq!(count("<p>That is un<em>believ</em>able.</p>"), 3);
+        assert_eq!(count("<div>One</div><div>two</div>"), 2);
+    }
+
+    #[test]
+    fn does_not_let_unclosed_elements_swallow_the_document() {
+        let result = count_with_options(
+            "<p>Unclosed paragraph\n\nMarkdown *text*.",
+            Options::empty(),
+        );
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn does_not_let_unclosed_divs_swallow_the_document() {
+        let result = count_with_options(
+            "<div class=\"note\">\nHello\n\nThe whole rest of the essay goes here.",
+            Options::empty(),
+        );
+        assert_eq!(result, 8);
+    }
+
+    #[test]
+    fn matches_nested_elements_closed_by_later_blocks() {
+        let result = count_with_options(
+            "<div>\n\nA.\n\n<div>\n\nB.\n\n</div>\n\nC.\n\n</div>\n\nD.",
+            Options::empty(),
+        );
+        assert_eq!(result, 1);
+    }
+
+    #[test]
+    fn handles_many_unclosed_blocks() {
+        let text = "<div>\n\npara words\n\n".repeat(10_000);
+        let result = count_with_options(&text, Options::empty());
+        assert_eq!(result, 20_000);
+
+        let text = "<section>\n\n## Heading\n\nwords\n\n".repeat(3_000) + "</section>";
+        let result = count_with_options(&text, Options::IncludeHeadings);
+        assert_eq!(result, 5_998);
+    }
+
+    #[test]
+    fn counts_markdown_inside_block_html_as_block_html() {
+        let result = report_with_options(
+            "<div>\n\nMarkdown inside.\n\n</div>\n\nOutside.",
+            Options::empty(),
+        );
+        assert_eq!(result.total(), 1);
+        assert_eq!(result.tally(Category::BlockHtml).excluded, 2);
+    }
+}
+
+mod exclude_html {
+    use super::*;
+
+    #[test]
+    fn parses_matchers() {
+        assert_eq!("aside".parse(), Ok(HtmlMatcher::Tag("aside".into())));
+        assert_eq!("DIV".parse(), Ok(HtmlMatcher::Tag("div".into())));
+        assert_eq!(".nocount".parse(), Ok(HtmlMatcher::Class("nocount".into())));
+        assert_eq!(
+            "[data-nocount]".parse(),
+            Ok(HtmlMatcher::Attribute {
+                name: "data-nocount".into(),
+                value: None
+            })
+        );
+        assert_eq!(
+            "[data-count=\"no\"]".parse(),
+            Ok(HtmlMatcher::Attribute {
+                name: "data-count".into(),
+                value: Some("no".into())
+            })
+        );
+        assert!("".parse::<HtmlMatcher>().is_err());
+        assert!(".".parse::<HtmlMatcher>().is_err());
+        assert!("[data-nocount".parse::<HtmlMatcher>().is_err());
+        assert!("div p".parse::<HtmlMatcher>().is_err());
+    }
+
+    #[test]
+    fn excludes_block_elements_by_tag() {
+        let text = "Text.\n\n<aside>Some aside words.</aside>";
+        assert_eq!(count_with_config(text, &Config::default()), 4);
+        let config = Config {
+            exclude_html: parse_all(&["aside"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 1);
+    }
+
+    #[test]
+    fn excludes_inline_elements_by_class() {
+        let text = "Some <span class=\"draft editor-note\">hidden *words*</span> text.";
+        assert_eq!(count_with_config(text, &Config::default()), 4);
+        let config = Config {
+            exclude_html: parse_all(&[".editor-note"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+    }
+
+    #[test]
+    fn excludes_elements_by_attribute() {
+        let text = "<p data-count=no>One</p>\n\n<p data-count=yes>Two</p>";
+        let config = Config {
+            exclude_html: parse_all(&["[data-count]"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 0);
+        let config = Config {
+            exclude_html: parse_all(&["[data-count=no]"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 1);
+    }
+
+    #[test]
+    fn excludes_markdown_inside_excluded_block_elements() {
+        let text = "<div class=\"sidebar\">\n\nSidebar *Markdown* text.\n\n</div>\n\nMain text.";
+        let config = Config {
+            exclude_html: parse_all(&[".sidebar"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+    }
+
+    #[test]
+    fn handles_nested_elements() {
+        let text = "<div class=nocount><div>Inner</div> still out</div>\n\nIn.";
+        let config = Config {
+            exclude_html: parse_all(&[".nocount"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 1);
+    }
+
+    #[test]
+    fn closes_unclosed_inline_elements_at_the_end_of_the_block() {
+        let text = "Some <span class=nocount>hidden\n\nVisible again.";
+        let config = Config {
+            exclude_html: parse_all(&[".nocount"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 3);
+    }
+}
+
+mod directives {
+    use super::*;
+
+    #[test]
+    fn turns_counting_off_and_on() {
+        let result = report(
+            "One two.
+
+<!-- count-md: off -->
+
+Sponsor blurb here.
+
+<!-- count-md: on -->
+
+Three.",
+        );
+        assert_eq!(result.total(), 3);
+        assert_eq!(
+            result.directives(),
+            &[DirectiveTally {
+                directive: Directive::Off,
+                excluded: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn turns_counting_off_to_the_end_of_the_document() {
+        let result = count("One.\n\n<!--count-md:off-->\n\nTwo three.");
+        assert_eq!(result, 1);
+    }
+
+    #[test]
+    fn handles_inline_directives() {
+        let result = count("One <!-- count-md: off -->two three<!-- count-md: on --> four.");
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn ignores_the_next_block() {
+        let result = report(
+            "One.
+
+<!-- count-md: ignore-next -->
+- Two three
+- Four
+
+Five.",
+        );
+        assert_eq!(result.total(), 2);
+        assert_eq!(result.directives()[0].directive, Directive::IgnoreNext);
+        assert_eq!(result.directives()[0].excluded, 3);
+    }
+
+    #[test]
+    fn tallies_only_words_which_would_have_been_counted() {
+        let result = report("<!-- count-md: off -->\n\nOne.\n\n> Quoted.");
+        assert_eq!(result.total(), 0);
+        assert_eq!(result.directives()[0].excluded, 1);
+        assert_eq!(result.tally(Category::Blockquotes).excluded, 1);
+    }
+
+    #[test]
+    fn ignores_other_comments() {
+        assert_eq!(
+            count("One <!-- count-md: nonsense --> two <!-- off -->three."),
+            3
+        );
+    }
+}
+
+mod nesting {
+    use super::*;
+
+    #[test]
+    fn excludes_blockquotes_inside_footnotes() {
+        let text = "Text.[^fn]\n\n[^fn]: Note.\n\n    > Quoted in a note.";
+        assert_eq!(count_with_options(text, Options::IncludeFootnotes), 2);
+        assert_eq!(
+            count_with_options(
+                text,
+                Options::IncludeFootnotes | Options::IncludeBlockquotes
+            ),
+            6
+        );
+    }
+
+    #[test]
+    fn excludes_tables_inside_footnotes() {
+        let text = "Text.[^fn]\n\n[^fn]: Note.\n\n    | a | b |\n    | - | - |\n    | c | d |";
+        assert_eq!(count_with_options(text, Options::IncludeFootnotes), 2);
+        assert_eq!(count_with_options(text, Options::IncludeTables), 1);
+    }
+
+    #[test]
+    fn handles_code_blocks_inside_blockquotes_inside_lists() {
+        let text = "- Item\n\n  > Quote\n  >\n  > ```\n  > code here\n  > ```\n\n  More item.";
+        assert_eq!(count_with_options(text, Options::empty()), 3);
+        assert_eq!(count_with_options(text, Options::IncludeBlockquotes), 4);
+        assert_eq!(
+            count_with_options(
+                text,
+                Options::IncludeBlockquotes | Options::IncludeBlockCode
+            ),
+            6
+        );
+    }
+
+    #[test]
+    fn handles_very_deeply_nested_blockquotes() {
+        let text = format!("Text.\n\n{} Deep.\n\nMore.", ">".repeat(300));
+        assert_eq!(count_with_options(&text, Options::empty()), 2);
+        assert_eq!(count_with_options(&text, Options::IncludeBlockquotes), 3);
+    }
+}
+
+mod filter {
+    use super::*;
+    use pulldown_cmark::{Event, HeadingLevel, Tag, TagEnd};
+
+    /// Skips the first paragraph after each H2, e.g. a standfirst.
+    #[derive(Default)]
+    struct SkipFirstParagraphAfterH2 {
+        after_h2: bool,
+        skipping: bool,
+    }
+
+    impl CountFilter for SkipFirstParagraphAfterH2 {
+        fn observe(&mut self, event: &Event<'_>) {
+            match event {
+                Event::End(TagEnd::Heading(HeadingLevel::H2)) => self.after_h2 = true,
+                Event::Start(Tag::Paragraph) => {
+                    self.skipping = std::mem::take(&mut self.after_h2);
+                }
+                Event::Start(_) => self.after_h2 = false,
+                Event::End(TagEnd::Paragraph) => self.skipping = false,
+                _ => {}
+            }
+        }
+
+        fn includes(&mut self, context: &Context<'_, '_>, text: &str) -> bool {
+            let mut options = Options::DEFAULT;
+            !self.skipping && options.includes(context, text)
+        }
+    }
+
+    #[test]
+    fn uses_custom_filters() {
+        let text = "## Heading\n\nSkip this.\n\nCount this.\n\n## Another\n\n- Not a paragraph.";
+        let result = report_with_filter(
+            text,
+            &Config::default(),
+            SkipFirstParagraphAfterH2::default(),
+        );
+        assert_eq!(result.total(), 7);
+        assert_eq!(result.excluded(), 2);
+        assert_eq!(result.excluded_by(Rule::Filter), 2);
+    }
+
+    #[test]
+    fn options_are_the_default_filter() {
+        let text = "# Title\n\nText.\n\n> Quote.\n\n`code`";
+        for options in [Options::DEFAULT, Options::empty(), Options::all()] {
+            assert_eq!(
+                count_with_filter(text, &Config::default(), options),
+                count_with_options(text, options)
+            );
+        }
+    }
+
+    struct Recorder(Vec<(String, TextKind, bool, bool)>);
+
+    impl CountFilter for Recorder {
+        fn includes(&mut self, context: &Context<'_, '_>, text: &str) -> bool {
+            self.0.push((
+                text.to_string(),
+                context.kind(),
+                context.inside(|tag| matches!(tag, Tag::BlockQuote(_))),
+                context.is_in(Category::Footnotes),
+            ));
+            true
+        }
+    }
+
+    #[test]
+    fn describes_the_context_of_each_fragment() {
+        let mut recorder = Recorder(Vec::new());
+        let text = "Hi `code`.[^fn]\n\n[^fn]: > Quoted $x$.";
+        let result = report_with_filter(text, &Config::from(Options::all()), &mut recorder);
+        assert_eq!(result.total(), 3);
+        assert_eq!(
+            recorder.0,
+            vec![
+                ("Hi ".to_string(), TextKind::Text, false, false),
+                ("code".to_string(), TextKind::InlineCode, false, false),
+                (".".to_string(), TextKind::Text, false, false),
+                ("Quoted ".to_string(), TextKind::Text, true, true),
+                ("x".to_string(), TextKind::InlineMath, true, true),
+                (".".to_string(), TextKind::Text, true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn still_applies_html_rules_and_directives() {
+        let config = Config {
+            exclude_html: vec![".nocount".parse().unwrap()],
+            ..Config::from(Options::all())
+        };
+        let text = "One <span class=nocount>two</span>\n\n<!-- count-md: off -->\n\nThree.";
+        assert_eq!(count_with_filter(text, &config, Options::all()), 1);
+    }
+}
+
+mod dialect {
+    use super::*;
+    use pulldown_cmark::Options as CmarkOptions;
+
+    const TEXT: &str = "Some ~~deleted~~ text.\n\n| a | b |\n| - | - |\n| c | d |";
+
+    #[test]
+    fn all_by_default() {
+        assert_eq!(Config::default().extensions, Dialect::All.extensions());
+        let result = report(TEXT);
+        assert_eq!(result.tally(Category::Tables).included, 4);
+    }
+
+    #[test]
+    fn commonmark() {
+        let config = Config {
+            extensions: Dialect::CommonMark.extensions(),
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 7);
+        assert_eq!(result.categories().count(), 0);
+    }
+
+    #[test]
+    fn gfm() {
+        let config = Config {
+            extensions: Dialect::Gfm.extensions(),
+            ..Config::editorial()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 6);
+        assert_eq!(result.tally(Category::Strikethrough).excluded, 1);
+    }
+
+    #[test]
+    fn without_one_extension() {
+        let mut extensions = Dialect::Gfm.extensions();
+        extensions.remove(CmarkOptions::ENABLE_TABLES);
+        let config = Config {
+            extensions,
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.tally(Category::Tables), Tally::default());
+    }
+
+    #[test]
+    fn with_one_extension() {
+        let extensions = Dialect::CommonMark.extensions() | CmarkOptions::ENABLE_MATH;
+        let config = Config {
+            extensions,
+            ..Config::default()
+        };
+        let result = report_with_config("Where $x$ is large.", &config);
+        assert_eq!(result.tally(Category::InlineMath).excluded, 1);
+    }
+}
+
+mod exclude_sections {
+    use super::*;
+
+    const TEXT: &str = "# Paper
+
+Some text.
+
+## Method
+
+We did things.
+
+### References
+
+Some cited work.
+
+## Results
+
+It worked.
+
+## Acknowledgements
+
+Thanks to everyone.
+
+# Appendix A
+
+Extra material.
+";
+
+    #[test]
+    fn by_exact_text() {
+        let config = Config {
+            exclude_sections: parse_all(&["Acknowledgements"]),
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 18);
+        assert_eq!(result.excluded(), 4);
+    }
+
+    #[test]
+    fn includes_subsections() {
+        let config = Config {
+            exclude_sections: parse_all(&["Method"]),
+            ..Config::default()
+        };
+        let result = count_with_config(TEXT, &config);
+        assert_eq!(result, 14);
+    }
+
+    #[test]
+    fn ends_at_same_or_higher_level() {
+        let config = Config {
+            exclude_sections: parse_all(&["Paper"]),
+            ..Config::default()
+        };
+        let result = count_with_config(TEXT, &config);
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    fn by_level() {
+        let config = Config {
+            exclude_sections: parse_all(&["## References"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 22);
+        let config = Config {
+            exclude_sections: parse_all(&["### References"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 18);
+    }
+
+    #[test]
+    fn by_regex() {
+        let config = Config {
+            exclude_sections: parse_all(&["/^(References|Acknowledgements)$/", "/^Appendix/"]),
+            ..Config::default()
+        };
+        let result = count_with_config(TEXT, &config);
+        assert_eq!(result, 10);
+    }
+
+    #[test]
+    fn by_text_with_markup() {
+        let config = Config {
+            exclude_sections: parse_all(&["The End"]),
+            ..Config::default()
+        };
+        let result = count_with_config("## The *End*\n\nDone.\n\n## Next\n\nMore.", &config);
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn only_by_top_level_headings() {
+        let config = Config {
+            exclude_sections: parse_all(&["Notes"]),
+            ..Config::default()
+        };
+        let quoted = "# Chapter\n\nSome body words.\n\n> ## Notes\n> quoted\n\nChapter body continues here with many words.";
+        assert_eq!(count_with_config(quoted, &config), 11);
+        let listed = "# Chapter\n\nSome body words.\n\n- ## Notes\n\nChapter body continues here with many words.";
+        assert_eq!(count_with_config(listed, &config), 12);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("".parse::<HeadingMatcher>().is_err());
+        assert!("#######  Too deep".parse::<HeadingMatcher>().is_err());
+        assert!("##NoSpace".parse::<HeadingMatcher>().is_err());
+        assert!("/(unclosed/".parse::<HeadingMatcher>().is_err());
+        assert_eq!(
+            "## /^Notes?$/".parse::<HeadingMatcher>().unwrap().level,
+            Some(pulldown_cmark::HeadingLevel::H2)
+        );
+    }
+}
+
+mod select_section {
+    use super::*;
+
+    const TEXT: &str = "# Book
+
+Front matter.
+
+## Part 1
+
+### Chapter 3
+
+Early chapter.
+
+## Part 2
+
+Part intro.
+
+### Chapter 3
+
+The real chapter three.
+
+#### Notes
+
+A note.
+
+### Chapter 4
+
+Later chapter.
+";
+
+    #[test]
+    fn by_heading() {
+        let config = Config {
+            section: Some("## Part 1".parse().unwrap()),
+            ..Config::default()
+        };
+        let result = count_with_config(TEXT, &config);
+        assert_eq!(result, 6);
+    }
+
+    #[test]
+    fn by_path() {
+        let config = Config {
+            section: Some("Book > Part 2 > Chapter 3".parse().unwrap()),
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 9);
+    }
+
+    #[test]
+    fn by_path_skipping_levels() {
+        let config = Config {
+            section: Some("Book > Notes".parse().unwrap()),
+            ..Config::default()
+        };
+        let result = count_with_config(TEXT, &config);
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn every_match() {
+        let config = Config {
+            section: Some("Chapter 3".parse().unwrap()),
+            ..Config::default()
+        };
+        let result = count_with_config(TEXT, &config);
+        assert_eq!(result, 13);
+    }
+
+    #[test]
+    fn no_match() {
+        let config = Config {
+            section: Some("Part 3 > Chapter 3".parse().unwrap()),
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 0);
+        assert_eq!(result.excluded(), count(TEXT));
+    }
+
+    #[test]
+    fn with_excluded_sections() {
+        let config = Config {
+            section: Some("Part 2 > Chapter 3".parse().unwrap()),
+            exclude_sections: parse_all(&["Notes"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 6);
+    }
+
+    #[test]
+    fn only_by_top_level_headings() {
+        let config = Config {
+            section: Some("Abstract".parse().unwrap()),
+            ..Config::default()
+        };
+        let text = "# Abstract\n\nFirst words.[^a]\n\n[^a]: # Other\n\nMore abstract words.\n\n# Introduction\n\nNot counted.";
+        assert_eq!(count_with_config(text, &config), 7);
+    }
+}
+
+mod selectors {
+    use super::*;
+
+    #[test]
+    fn child() {
+        let text = "> Outer quote.\n>\n> > Inner quote.\n\n> > > Deepest quote here.";
+        let config = Config {
+            exclude: parse_all(&["blockquote > blockquote"]),
+            ..Config::from(Options::DEFAULT | Options::IncludeBlockquotes)
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+    }
+
+    #[test]
+    fn descendant() {
+        let text = "- Top item\n  - Nested item\n    - Deeper item";
+        let config = Config {
+            exclude: parse_all(&["list list"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+        let config = Config {
+            exclude: parse_all(&["list > list"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 6);
+        let config = Config {
+            exclude: parse_all(&["item > list"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+    }
+
+    #[test]
+    fn table_header() {
+        let text = "| Name | Role |\n| - | - |\n| Ada | Engineer |";
+        let config = Config {
+            exclude: parse_all(&["table thead"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+    }
+
+    #[test]
+    fn heading_level() {
+        let text = "# One\n\n## Two\n\n### Three\n\n#### Four";
+        let config = Config {
+            exclude: parse_all(&["heading[level>=3]"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 2);
+    }
+
+    #[test]
+    fn include_overrides_options() {
+        let text = "Text.[^fn]\n\n[^fn]: A note.\n\n    > Quoted in a note.";
+        let config = Config {
+            include: vec!["footnote blockquote".parse().unwrap()],
+            ..Config::from(Options::empty())
+        };
+        assert_eq!(count_with_config(text, &config), 5);
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let text = "Some `code` here.";
+        let config = Config {
+            include: parse_all(&["paragraph"]),
+            exclude: parse_all(&["paragraph"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 0);
+    }
+
+    #[test]
+    fn conditions() {
+        let text = "```rust\nfn main() {}\n```\n\n```python\ndef main(): pass\n```\n\n> [!NOTE]\n> A note.";
+        let config = Config {
+            include: vec![
+                "code[lang=rust]".parse().unwrap(),
+                "alert[kind=note]".parse().unwrap(),
+            ],
+            ..Config::from(Options::empty())
+        };
+        assert_eq!(count_with_config(text, &config), 4);
+    }
+
+    #[test]
+    fn parse_errors() {
+        for selector in [
+            "",
+            "blockquote >",
+            "nope",
+            "heading[level]",
+            "heading[size=2]",
+            "heading[level>=3",
+            "heading[level>=three]",
+            "heading[level=]x",
+            "alert[kind=notes]",
+            "table,thead",
+        ] {
+            assert!(selector.parse::<Selector>().is_err(), "{selector}");
+        }
+    }
+}
+
+mod table_parts {
+    use super::*;
+
+    const TEXT: &str = "| ID | Type | Description |
+| -- | ---- | ----------- |
+| A1 | enum | The first thing we need. |
+| B2 | struct | Another thing entirely. |";
+
+    #[test]
+    fn all() {
+        assert_eq!(count_with_config(TEXT, &Config::default()), 15);
+    }
+
+    #[test]
+    fn header_only() {
+        let config = Config {
+            table_rows: TableRows::Header,
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 3);
+    }
+
+    #[test]
+    fn body_only() {
+        let config = Config {
+            table_rows: TableRows::Body,
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 12);
+        assert_eq!(result.excluded_by(Rule::TableRows), 3);
+    }
+
+    #[test]
+    fn exclude_columns_by_index_and_header() {
+        let config = Config {
+            exclude_columns: parse_all(&["1", "type"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 9);
+    }
+
+    #[test]
+    fn columns_are_per_table() {
+        let text = format!("{TEXT}\n\n| Name | ID |\n| - | - |\n| Ada | X9 |");
+        let config = Config {
+            table_rows: TableRows::Body,
+            exclude_columns: parse_all(&["ID"]),
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(&text, &config), 11);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("0".parse::<ColumnMatcher>().is_err());
+        assert!(" ".parse::<ColumnMatcher>().is_err());
+        assert_eq!("3".parse(), Ok(ColumnMatcher::Index(3)));
+        assert_eq!(
+            "Type".parse(),
+            Ok(ColumnMatcher::Header("Type".to_string()))
+        );
+    }
+}
+
+mod heading_levels {
+    use super::*;
+    use pulldown_cmark::HeadingLevel;
+
+    const TEXT: &str =
+        "# The Chapter Title\n\nSome text.\n\n## A Section\n\n### A Small Subheading\n\n#### Tiny";
+
+    #[test]
+    fn max_level() {
+        let config = Config {
+            max_heading_level: Some(HeadingLevel::H2),
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(result.total(), 7);
+        assert_eq!(result.excluded_by(Rule::HeadingLevel), 4);
+        assert_eq!(result.tally(Category::Headings).excluded, 0);
+    }
+
+    #[test]
+    fn tallied_per_level() {
+        let config = Config {
+            max_heading_level: Some(HeadingLevel::H2),
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(
+            result.heading_levels().collect::<Vec<_>>(),
+            [
+                (
+                    HeadingLevel::H1,
+                    Tally {
+                        included: 3,
+                        excluded: 0
+                    }
+                ),
+                (
+                    HeadingLevel::H2,
+                    Tally {
+                        included: 2,
+                        excluded: 0
+                    }
+                ),
+                (
+                    HeadingLevel::H3,
+                    Tally {
+                        included: 0,
+                        excluded: 3
+                    }
+                ),
+                (
+                    HeadingLevel::H4,
+                    Tally {
+                        included: 0,
+                        excluded: 1
+                    }
+                ),
+            ]
+        );
+        assert_eq!(result.heading_level(HeadingLevel::H5), Tally::default());
+    }
+
+    #[test]
+    fn tallied_when_headings_excluded() {
+        let result = report_with_options(TEXT, Options::empty());
+        assert_eq!(result.total(), 2);
+        assert_eq!(result.heading_level(HeadingLevel::H1).excluded, 3);
+    }
+}
+
+mod quote_depths {
+    use super::*;
+
+    const TEXT: &str = "My reply.\n\n> Their message.\n>\n> > My earlier message here.\n> >\n> > > The original one.";
+
+    #[test]
+    fn unlimited() {
+        let result = count_with_options(TEXT, Options::DEFAULT | Options::IncludeBlockquotes);
+        assert_eq!(result, 11);
+    }
+
+    #[test]
+    fn max_depth() {
+        let config = Config {
+            max_blockquote_depth: Some(1),
+            ..Config::from(Options::DEFAULT | Options::IncludeBlockquotes)
+        };
+        assert_eq!(count_with_config(TEXT, &config), 4);
+        let config = Config {
+            max_blockquote_depth: Some(2),
+            ..Config::from(Options::DEFAULT | Options::IncludeBlockquotes)
+        };
+        assert_eq!(count_with_config(TEXT, &config), 8);
+    }
+
+    #[test]
+    fn tallied_per_depth() {
+        let config = Config {
+            max_blockquote_depth: Some(1),
+            ..Config::from(Options::DEFAULT | Options::IncludeBlockquotes)
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(
+            result.quote_depths().collect::<Vec<_>>(),
+            [
+                (
+                    1,
+                    Tally {
+                        included: 2,
+                        excluded: 0
+                    }
+                ),
+                (
+                    2,
+                    Tally {
+                        included: 0,
+                        excluded: 4
+                    }
+                ),
+                (
+                    3,
+                    Tally {
+                        included: 0,
+                        excluded: 3
+                    }
+                ),
+            ]
+        );
+        assert_eq!(result.quote_depth(4), Tally::default());
+    }
+
+    #[test]
+    fn alerts_do_not_add_depth() {
+        let text = "> [!NOTE]\n> A note.\n>\n> > A quote in it.";
+        let config = Config {
+            max_blockquote_depth: Some(1),
+            ..Config::from(Options::IncludeAlerts | Options::IncludeBlockquotes)
+        };
+        assert_eq!(count_with_config(text, &config), 6);
+    }
+}
+
+mod code_languages {
+    use super::*;
+
+    const TEXT: &str = "Some prose.\n\n```text\nExample prose here.\n```\n\n```rust,ignore\nfn main() {}\n```\n\n```Markdown\n*Some* words.\n```";
+
+    #[test]
+    fn counted_like_prose() {
+        assert_eq!(count_with_config(TEXT, &Config::default()), 2);
+        let config = Config {
+            code_languages: vec![String::from("text")],
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 5);
+        let config = Config {
+            code_languages: vec![String::from("text"), String::from("markdown")],
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(TEXT, &config), 7);
+    }
+
+    #[test]
+    fn still_subject_to_other_options() {
+        let text = "> ```text\n> Quoted example.\n> ```";
+        let config = Config {
+            code_languages: vec![String::from("text")],
+            ..Config::default()
+        };
+        assert_eq!(count_with_config(text, &config), 0);
+    }
+
+    #[test]
+    fn tallied_per_language() {
+        let config = Config {
+            code_languages: vec![String::from("text")],
+            ..Config::default()
+        };
+        let result = report_with_config(TEXT, &config);
+        assert_eq!(
+            result.code_languages().collect::<Vec<_>>(),
+            [
+                (
+                    "text",
+                    Tally {
+                        included: 3,
+                        excluded: 0
+                    }
+                ),
+                (
+                    "rust",
+                    Tally {
+                        included: 0,
+                        excluded: 2
+                    }
+                ),
+                (
+                    "markdown",
+                    Tally {
+                        included: 0,
+                        excluded: 2
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            result.tally(Category::BlockCode),
+            Tally {
+                included: 3,
+                excluded: 4
+            }
+        );
+        assert_eq!(result.code_language("RUST").excluded, 2);
+        assert_eq!(result.code_language("json"), Tally::default());
+    }
+}
+
+mod options {
+    use super::*;
+
+    mod inline_code {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options("This is `code`.", Options::IncludeInlineCode);
+            assert_eq!(result, 3);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options("This is `code`.", Options::empty());
+            assert_eq!(result, 2);
+        }
+    }
+
+    mod block_code {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                "This is code:
+
+```rust
+fn main() { }
+```
+",
+                Options::IncludeBlockCode,
+            );
+            assert_eq!(result, 5);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                "This is code:
+
+    ```rust
+    fn main() { }
+    ```
+    ",
+                Options::empty(),
+            );
+            assert_eq!(result, 3);
+        }
+    }
+
+    mod tables {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                r#"Some text
+
+| thead 1 | thead 2 |
+| ------- | ------- |
+| foo     | bar     |
+| baz     | quux    |
+
+More stuff"#,
+                Options::IncludeTables,
+            );
+
+            assert_eq!(result, 12);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                r#"Some text
+
+| thead 1 | thead 2 |
+| ------- | ------- |
+| foo     | bar     |
+| baz     | quux    |
+
+More stuff"#,
+                Options::empty(),
+            );
+
+            assert_eq!(result, 4);
+        }
+    }
+
+    mod footnotes {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                "Some text.[^fn]\n\n[^fn]: Footnote definition text!",
+                Options::IncludeFootnotes,
+            );
+            assert_eq!(result, 5);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                "Some text.[^fn]\n\n[^fn]: Footnote definition text!",
+                Options::empty(),
+            );
+            assert_eq!(result, 2);
+        }
+    }
+
+    mod block_html {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                "Some text.\n\n<div>Block HTML content.\n\nWith newlines!</div>\n\nMore text.",
+                Options::IncludeBlockHtml,
+            );
+            assert_eq!(result, 9);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                "Some text.\n\n<div>Block HTML content.\n\nWith newlines!</div>\n\nMore text.",
+                Options::empty(),
+            );
+            assert_eq!(result, 4);
+        }
+    }
+
+    mod blockquotes {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                "Text.\n\n> Blockquote text.\n\nMore text.",
+                Options::IncludeBlockquotes,
+            );
+            assert_eq!(result, 5);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                "Text.\n\n> Blockquote text.\n\nMore text.",
+                Options::empty(),
+            );
+            assert_eq!(result, 3);
+        }
+    }
+
+    mod metadata {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                "---\ntitle: What a Cool Thing\n---\n\nNeato!",
+                Options::IncludeMetadata,
+            );
+            assert_eq!(result, 6);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                "---\ntitle: What a Cool Thing\n---\n\nNeato!",
+                Options::empty(),
+            );
+            assert_eq!(result, 1);
+        }
+    }
+
+    mod headings {
+        use super::*;
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(
+                "# H1\n\n## H2\n\n### H3\n\n#### H4\n\n##### H5\n\n###### H6\n\nwhoa",
+                Options::IncludeHeadings,
+            );
+            assert_eq!(result, 7);
+        }
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(
+                "# H1\n\n## H2\n\n### H3\n\n#### H4\n\n##### H5\n\n###### H6\n\nwhoa",
+                Options::empty(),
+            );
+            assert_eq!(result, 1);
+        }
+    }
+
+    mod math {
+        use super::*;
+
+        const TEXT: &str = r#"Energy $E = mc^2$ where $\text{c is the speed of light}$.
+
+$$
+\sum_i x_i \quad \mathrm{for\ all}\ \textbf{\{i\}}
+$$"#;
+
+        #[test]
+        fn disabled() {
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 2);
+        }
+
+        #[test]
+        fn as_expressions() {
+            let config = Config {
+                math: MathMode::Expression,
+                ..Config::from(Options::empty())
+            };
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(result.total(), 5);
+            assert_eq!(result.tally(Category::InlineMath).included, 2);
+            assert_eq!(result.tally(Category::DisplayMath).included, 1);
+        }
+
+        #[test]
+        fn text_only() {
+            let config = Config {
+                math: MathMode::TextOnly,
+                ..Config::from(Options::empty())
+            };
+            assert_eq!(count_with_config(TEXT, &config), 11);
+        }
+
+        #[test]
+        fn text_only_ignores_similar_commands() {
+            let config = Config {
+                math: MathMode::TextOnly,
+                ..Config::from(Options::empty())
+            };
+            assert_eq!(count_with_config(r"$\textstyle x + \texts{y}$", &config), 0);
+        }
+
+        #[test]
+        fn not_changed_by_all_options() {
+            assert_eq!(count_with_options("Let $x$ be.", Options::all()), 2);
+
+            let config = Config {
+                math: MathMode::Expression,
+                ..Config::from(Options::all())
+            };
+            assert_eq!(count_with_config("Let $x$ be.", &config), 3);
+        }
+    }
+
+    mod urls {
+        use super::*;
+
+        const TEXT: &str =
+            "Read <https://example.com/a-post> or www.example.org/page, (see https://example.net).";
+
+        #[test]
+        fn as_single_words() {
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 6);
+        }
+
+        #[test]
+        fn excluded() {
+            let config = Config {
+                urls: UrlMode::Exclude,
+                ..Config::from(Options::empty())
+            };
+            assert_eq!(count_with_config(TEXT, &config), 3);
+        }
+
+        #[test]
+        fn split_by_markup() {
+            let exclude = Config {
+                urls: UrlMode::Exclude,
+                ..Config::from(Options::empty())
+            };
+            for url in [
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                "https://example.com/~user/page",
+                "https://example.com/search?a=1&amp;b=2",
+                "https://example.com/a_b_(c)",
+                "https://example.com/path/[x]",
+                "https://example.com/a~~b~~c",
+            ] {
+                let text = format!("Read {url} today.");
+                assert_eq!(count_with_config(&text, &exclude), 2, "{url}");
+                assert_eq!(count_with_options(&text, Options::empty()), 3, "{url}");
+            }
+        }
+
+        #[test]
+        fn as_segments() {
+            let config = Config {
+                urls: UrlMode::Segments,
+                ..Config::from(Options::empty())
+            };
+            assert_eq!(count_with_config(TEXT, &config), 11);
+        }
 
-    mod inline_code {
-        use super::*;
+        #[test]
+        fn not_changed_by_all_options() {
+            let result = count_with_options("See https://example.com/a/b now.", Options::all());
+            assert_eq!(result, 3);
+        }
 
         #[test]
-        fn enabled() {
-            let result = count_with_options("This is `code`.", Options::IncludeInlineCode);
+        fn email_autolinks() {
+            let result = count_with_options("Write to <someone@example.com>.", Options::empty());
             assert_eq!(result, 3);
         }
 
         #[test]
-        fn disabled() {
-            let result = count_with_options("This is `code`.", Options::empty());
+        fn do_not_join_with_surrounding_text() {
+            let result = count_with_options("*see*https://example.com", Options::empty());
             assert_eq!(result, 2);
         }
-    }
-
-    mod block_code {
-        use super::*;
 
         #[test]
-        fn enabled() {
-            let result = count_with_options(
-                "This is code:
-
-```rust
-fn main() { }
-```
-",
-                Options::IncludeBlockCode,
-            );
-            assert_eq!(result, 5);
+        fn not_inside_words() {
+            let result = count_with_options("nothttp://example", Options::empty());
+            assert_eq!(result, 2);
         }
 
         #[test]
-        fn disabled() {
+        fn link_destinations_are_not_counted() {
             let result = count_with_options(
-                "This is code:
-
-    ```rust
-    fn main() { }
-    ```
-    ",
+                "A [good post](https://example.com/a-good-post).",
                 Options::empty(),
             );
             assert_eq!(result, 3);
         }
     }
 
-    mod tables {
+    mod image_text {
         use super::*;
 
+        const TEXT: &str = r#"A ![diagram of the system](system.png "System overview") and a [link](https://example.com "More details").
+
+<img src="cat.png" alt="A sleeping cat" title="Whiskers"> Nice."#;
+
         #[test]
         fn enabled() {
-            let result = count_with_options(
-                r#"Some text
-
-| thead 1 | thead 2 |
-| ------- | ------- |
-| foo     | bar     |
-| baz     | quux    |
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 9);
+        }
 
-More stuff"#,
-                Options::IncludeTables,
-            );
+        #[test]
+        fn with_attributes() {
+            let config = Config {
+                attributes: true,
+                ..Config::from(Options::empty())
+            };
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(result.total(), 17);
+            assert_eq!(result.tally(Category::Attributes).included, 8);
+        }
 
-            assert_eq!(result, 12);
+        #[test]
+        fn only_img_attributes() {
+            let config = Config {
+                attributes: true,
+                ..Config::default()
+            };
+            let text = r#"The <abbr title="HyperText Markup Language">HTML</abbr> spec, see [docs](https://x.org "Official documentation page")."#;
+            assert_eq!(count(text), 5);
+            assert_eq!(count_with_config(text, &config), 8);
+
+            let text = r#"<div title="tooltip words here">Body</div>"#;
+            assert_eq!(count_with_config(text, &config), 1);
         }
 
         #[test]
         fn disabled() {
-            let result = count_with_options(
-                r#"Some text
+            let config = Config {
+                image_text: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
+            assert_eq!(result, 5);
+        }
 
-| thead 1 | thead 2 |
-| ------- | ------- |
-| foo     | bar     |
-| baz     | quux    |
+        #[test]
+        fn excluded_with_html_element() {
+            let mut config = Config::from(Options::empty());
+            config.attributes = true;
+            config.exclude_html.push("img".parse().unwrap());
+            let result = report_with_config(r#"<img alt="A sleeping cat"> Nice."#, &config);
+            assert_eq!(result.total(), 1);
+            assert_eq!(result.excluded_by(Rule::Html), 3);
+        }
 
-More stuff"#,
-                Options::empty(),
+        #[test]
+        fn not_in_script() {
+            let result = count_with_options(
+                r#"<script>let x = "<img alt='hidden'>";</script>"#,
+                Options::IncludeBlockHtml,
             );
+            assert_eq!(result, 0);
+        }
+    }
 
+    mod definition_lists {
+        use super::*;
+
+        const TEXT: &str = "A glossary.\n\nMarkdown\n: A lightweight markup language.\n\nHTML\n: The markup language of the web.\n: Also used in email.";
+
+        #[test]
+        fn enabled() {
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 18);
+        }
+
+        #[test]
+        fn titles_only() {
+            let config = Config {
+                definitions: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
             assert_eq!(result, 4);
         }
+
+        #[test]
+        fn definitions_only() {
+            let config = Config {
+                definition_titles: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
+            assert_eq!(result, 16);
+        }
+
+        #[test]
+        fn tallied() {
+            let config = Config {
+                definitions: false,
+                ..Config::from(Options::empty())
+            };
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(
+                result.tally(Category::DefinitionTitles),
+                Tally {
+                    included: 2,
+                    excluded: 0
+                }
+            );
+            assert_eq!(
+                result.tally(Category::Definitions),
+                Tally {
+                    included: 0,
+                    excluded: 14
+                }
+            );
+        }
     }
 
-    mod footnotes {
+    mod lists {
         use super::*;
 
+        const TEXT: &str =
+            "Intro text.\n\n- First item\n  1. Nested step\n- Second item\n\n1. Numbered item";
+
         #[test]
         fn enabled() {
-            let result = count_with_options(
-                "Some text.[^fn]\n\n[^fn]: Footnote definition text!",
-                Options::IncludeFootnotes,
-            );
-            assert_eq!(result, 5);
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 10);
         }
 
         #[test]
         fn disabled() {
-            let result = count_with_options(
-                "Some text.[^fn]\n\n[^fn]: Footnote definition text!",
-                Options::empty(),
-            );
+            let config = Config {
+                ordered_lists: false,
+                unordered_lists: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
             assert_eq!(result, 2);
         }
+
+        #[test]
+        fn unordered_only() {
+            let config = Config {
+                ordered_lists: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
+            assert_eq!(result, 6);
+        }
+
+        #[test]
+        fn ordered_only() {
+            let config = Config {
+                unordered_lists: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
+            assert_eq!(result, 4);
+        }
+
+        #[test]
+        fn max_depth() {
+            let config = Config {
+                max_list_depth: Some(1),
+                ..Config::default()
+            };
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(result.total(), 8);
+            assert_eq!(result.excluded_by(Rule::ListDepth), 2);
+            assert_eq!(result.tally(Category::OrderedLists).excluded, 0);
+        }
     }
 
-    mod block_html {
+    mod tasks {
         use super::*;
 
+        const TEXT: &str = "Things to do:\n\n- [x] Write the draft\n- [ ] Edit the draft\n  - Check the facts\n- A plain item";
+
         #[test]
-        fn enabled() {
-            let result = count_with_options(
-                "Some text.\n\n<div>Block HTML content.\n\nWith newlines!</div>\n\nMore text.",
-                Options::IncludeBlockHtml,
-            );
+        fn all() {
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 15);
+        }
+
+        #[test]
+        fn checked_only() {
+            let config = Config {
+                unchecked_tasks: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
             assert_eq!(result, 9);
         }
 
         #[test]
-        fn disabled() {
-            let result = count_with_options(
-                "Some text.\n\n<div>Block HTML content.\n\nWith newlines!</div>\n\nMore text.",
-                Options::empty(),
-            );
-            assert_eq!(result, 4);
+        fn unchecked_only() {
+            let config = Config {
+                checked_tasks: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
+            assert_eq!(result, 12);
+        }
+
+        #[test]
+        fn tallied() {
+            let result = report(TEXT);
+            assert_eq!(result.tally(Category::CheckedTasks).included, 3);
+            assert_eq!(result.tally(Category::UncheckedTasks).included, 6);
         }
     }
 
-    mod blockquotes {
+    mod alerts {
         use super::*;
+        use pulldown_cmark::BlockQuoteKind;
+
+        const TEXT: &str = "Some text.\n\n> [!NOTE]\n> A useful note.\n\n> [!WARNING]\n> Be careful here.\n\n> A real quote.";
 
         #[test]
         fn enabled() {
-            let result = count_with_options(
-                "Text.\n\n> Blockquote text.\n\nMore text.",
-                Options::IncludeBlockquotes,
-            );
-            assert_eq!(result, 5);
+            let result = count_with_options(TEXT, Options::IncludeAlerts);
+            assert_eq!(result, 8);
         }
 
         #[test]
         fn disabled() {
-            let result = count_with_options(
-                "Text.\n\n> Blockquote text.\n\nMore text.",
-                Options::empty(),
+            let result = count_with_options(TEXT, Options::empty());
+            assert_eq!(result, 2);
+        }
+
+        #[test]
+        fn separate_from_blockquotes() {
+            let result = report_with_options(TEXT, Options::IncludeBlockquotes);
+            assert_eq!(result.total(), 5);
+            assert_eq!(result.tally(Category::Alerts).excluded, 6);
+            assert_eq!(result.tally(Category::Blockquotes).excluded, 0);
+        }
+
+        #[test]
+        fn excluded_by_kind() {
+            let config = Config {
+                exclude_alerts: vec![BlockQuoteKind::Warning, BlockQuoteKind::Caution],
+                ..Config::from(Options::IncludeAlerts)
+            };
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(result.total(), 5);
+            assert_eq!(
+                result.tally(Category::Alerts),
+                Tally {
+                    included: 3,
+                    excluded: 0
+                }
             );
-            assert_eq!(result, 3);
+            assert_eq!(result.excluded_by(Rule::Alert), 3);
         }
     }
 
-    mod metadata {
+    mod strikethrough {
         use super::*;
 
+        const TEXT: &str =
+            "The ~~old wording~~ new wording, with ^raised words^ and ~lowered text~ here.";
+
         #[test]
-        fn enabled() {
-            let result = count_with_options(
-                "---\ntitle: What a Cool Thing\n---\n\nNeato!",
-                Options::IncludeMetadata,
-            );
-            assert_eq!(result, 6);
+        fn default() {
+            let result = report(TEXT);
+            assert_eq!(result.total(), 12);
+            assert_eq!(result.tally(Category::Strikethrough).included, 2);
         }
 
         #[test]
-        fn disabled() {
-            let result = count_with_options(
-                "---\ntitle: What a Cool Thing\n---\n\nNeato!",
-                Options::empty(),
-            );
-            assert_eq!(result, 1);
+        fn editorial() {
+            let result = report_with_config(TEXT, &Config::editorial());
+            assert_eq!(result.total(), 10);
+            assert_eq!(result.tally(Category::Strikethrough).excluded, 2);
+        }
+
+        #[test]
+        fn superscript_and_subscript() {
+            let config = Config {
+                superscript: false,
+                subscript: false,
+                ..Config::from(Options::empty())
+            };
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(result.total(), 8);
+            assert_eq!(result.tally(Category::Subscript).excluded, 2);
+            assert_eq!(result.tally(Category::Superscript).excluded, 2);
         }
     }
 
-    mod headings {
+    mod wikilinks {
         use super::*;
 
+        const TEXT: &str = "See [[Some Page]] and [[notes/target-page|the shown text]].";
+
         #[test]
-        fn enabled() {
-            let result = count_with_options(
-                "# H1\n\n## H2\n\n### H3\n\n#### H4\n\n##### H5\n\n###### H6\n\nwhoa",
-                Options::IncludeHeadings,
-            );
+        fn count_only_display_text() {
+            let result = count_with_options(TEXT, Options::empty());
             assert_eq!(result, 7);
         }
 
         #[test]
         fn disabled() {
-            let result = count_with_options(
-                "# H1\n\n## H2\n\n### H3\n\n#### H4\n\n##### H5\n\n###### H6\n\nwhoa",
-                Options::empty(),
+            let config = Config {
+                wikilinks: false,
+                ..Config::from(Options::empty())
+            };
+            let result = count_with_config(TEXT, &config);
+            assert_eq!(result, 2);
+        }
+
+        #[test]
+        fn tallied_apart_from_link_text() {
+            let result = report("A [[Page|wiki link]] and a [normal link](https://example.com).");
+            assert_eq!(result.tally(Category::Wikilinks).included, 2);
+            assert_eq!(result.tally(Category::LinkText).included, 2);
+        }
+
+        #[test]
+        fn targets_are_listed() {
+            let result = report_with_options(TEXT, Options::empty());
+            assert_eq!(result.wikilinks(), ["Some Page", "notes/target-page"]);
+        }
+    }
+
+    mod markdown_code_blocks {
+        use super::*;
+
+        const TEXT: &str = "Intro.\n\n```markdown\n# Title\n\n*Some* words, [a link](https://example.com).\n\n> Quoted.\n```";
+
+        #[test]
+        fn flat_text_by_default() {
+            let result = count_with_options(TEXT, Options::DEFAULT | Options::IncludeBlockCode);
+            assert_eq!(result, 8);
+        }
+
+        #[test]
+        fn counted_as_markdown() {
+            let mut config = Config {
+                markdown_code_blocks: true,
+                ..Config::from(Options::DEFAULT | Options::IncludeBlockCode)
+            };
+            assert_eq!(count_with_config(TEXT, &config), 6);
+
+            config.options |= Options::IncludeBlockquotes;
+            assert_eq!(count_with_config(TEXT, &config), 7);
+        }
+
+        #[test]
+        fn not_changed_by_all_options() {
+            assert_eq!(count_with_options(TEXT, Options::all()), 8);
+        }
+
+        #[test]
+        fn still_code_blocks() {
+            let mut config = Config {
+                markdown_code_blocks: true,
+                ..Config::default()
+            };
+            assert_eq!(count_with_config(TEXT, &config), 1);
+
+            config.code_languages = vec![String::from("markdown")];
+            let result = report_with_config(TEXT, &config);
+            assert_eq!(result.total(), 6);
+            assert_eq!(result.tally(Category::BlockCode).included, 5);
+            assert_eq!(result.code_language("markdown").included, 5);
+        }
+
+        #[test]
+        fn nested() {
+            let text = "````md\nOuter *words*.\n\n```md\nInner **words**.\n```\n````";
+            let config = Config {
+                markdown_code_blocks: true,
+                ..Config::from(Options::IncludeBlockCode)
+            };
+            assert_eq!(count_with_config(text, &config), 4);
+        }
+
+        #[test]
+        fn nested_wikilinks_and_languages_are_not_listed() {
+            let text = "````md\nSee [[Page]].\n\n```python\nprint\n```\n````";
+            let config = Config {
+                markdown_code_blocks: true,
+                ..Config::from(Options::IncludeBlockCode)
+            };
+            let result = report_with_config(text, &config);
+            assert!(result.wikilinks().is_empty());
+            assert_eq!(
+                result.code_languages().collect::<Vec<_>>(),
+                [(
+                    "md",
+                    Tally {
+                        included: 3,
+                        excluded: 0
+                    }
+                )]
             );
-            assert_eq!(result, 1);
+        }
+
+        #[test]
+        fn headings_do_not_start_sections() {
+            let config = Config {
+                markdown_code_blocks: true,
+                ..Config::from(Options::DEFAULT | Options::IncludeBlockCode)
+            };
+            let result = report_with_config(TEXT, &config);
+            assert!(result.sections().is_empty());
+            assert_eq!(result.preamble(), 6);
+        }
+
+        #[test]
+        fn directives_do_not_apply() {
+            let text = "```md\n<!-- count-md: off -->\n\nAn example.\n```\n\nAfter it.";
+            let config = Config {
+                markdown_code_blocks: true,
+                ..Config::from(Options::DEFAULT | Options::IncludeBlockCode)
+            };
+            let result = report_with_config(text, &config);
+            assert_eq!(result.total(), 4);
+            assert!(result.directives().is_empty());
         }
     }
 
@@ -465,3 +2171,226 @@ Yay!"#,
         }
     }
 }
+
+mod report {
+    use super::*;
+
+    #[test]
+    fn total_matches_count() {
+        let text = "# Title\n\nSome text.[^fn]\n\n> A quote.\n\n[^fn]: A footnote.";
+        assert_eq!(report(text).total(), count(text));
+    }
+
+    #[test]
+    fn tallies_included_categories() {
+        let result = report("# Title\n\nSome text.[^fn]\n\n[^fn]: A longer footnote.");
+        assert_eq!(result.total(), 6);
+        assert_eq!(result.excluded(), 0);
+        assert_eq!(
+            result.tally(Category::Headings),
+            Tally {
+                included: 1,
+                excluded: 0
+            }
+        );
+        assert_eq!(
+            result.tally(Category::Footnotes),
+            Tally {
+                included: 3,
+                excluded: 0
+            }
+        );
+    }
+
+    #[test]
+    fn tallies_excluded_categories() {
+        let result = report("Some text.\n\n> A quote from elsewhere.");
+        assert_eq!(result.total(), 2);
+        assert_eq!(result.excluded(), 4);
+        assert_eq!(
+            result.tally(Category::Blockquotes),
+            Tally {
+                included: 0,
+                excluded: 4
+            }
+        );
+    }
+
+    #[test]
+    fn tallies_nested_categories_in_each() {
+        let result = report_with_options(
+            "Text.[^fn]\n\n[^fn]: Note.\n\n    | a | b |\n    | - | - |\n    | c | d |",
+            Options::IncludeFootnotes | Options::IncludeTables,
+        );
+        assert_eq!(result.total(), 6);
+        assert_eq!(
+            result.tally(Category::Footnotes),
+            Tally {
+                included: 5,
+                excluded: 0
+            }
+        );
+        assert_eq!(
+            result.tally(Category::Tables),
+            Tally {
+                included: 4,
+                excluded: 0
+            }
+        );
+    }
+
+    #[test]
+    fn charges_exclusions_only_to_the_categories_which_exclude_them() {
+        let result = report_with_options(
+            "Text.[^fn]\n\n[^fn]: Note.\n\n    | a | b |\n    | - | - |\n    | c | d |",
+            Options::IncludeFootnotes,
+        );
+        assert_eq!(result.total(), 2);
+        assert_eq!(
+            result.tally(Category::Footnotes),
+            Tally {
+                included: 1,
+                excluded: 0
+            }
+        );
+        assert_eq!(
+            result.tally(Category::Tables),
+            Tally {
+                included: 0,
+                excluded: 4
+            }
+        );
+    }
+
+    #[test]
+    fn tallies_exclusions_by_rule() {
+        let config = Config {
+            exclude: parse_all(&["list list"]),
+            exclude_sections: parse_all(&["Notes"]),
+            ..Config::default()
+        };
+        let result = report_with_config(
+            "- Top\n  - Nested item\n\n## Notes\n\nSome notes here.",
+            &config,
+        );
+        assert_eq!(result.total(), 1);
+        assert_eq!(
+            result.rules().collect::<Vec<_>>(),
+            [(Rule::Selector, 2), (Rule::Section, 4)]
+        );
+        assert_eq!(result.excluded_by(Rule::Html), 0);
+        assert_eq!(result.tally(Category::Headings).excluded, 0);
+    }
+
+    #[test]
+    fn excludes_inline_code_in_excluded_context() {
+        let result = report("Text.\n\n> Quoted `code`.");
+        assert_eq!(result.total(), 1);
+        assert_eq!(result.tally(Category::Blockquotes).excluded, 2);
+        assert_eq!(result.tally(Category::InlineCode), Tally::default());
+    }
+
+    #[test]
+    fn tallies_link_text_and_urls() {
+        let result = report("A [good post](https://example.com) at <https://example.com>.");
+        assert_eq!(result.total(), 5);
+        assert_eq!(
+            result.tally(Category::LinkText),
+            Tally {
+                included: 2,
+                excluded: 0
+            }
+        );
+        assert_eq!(
+            result.tally(Category::Urls),
+            Tally {
+                included: 1,
+                excluded: 0
+            }
+        );
+    }
+
+    #[test]
+    fn omits_empty_categories() {
+        let result = report("Just some text.");
+        assert_eq!(result.categories().count(), 0);
+    }
+}
+
+mod outline {
+    use super::*;
+    use pulldown_cmark::HeadingLevel;
+
+    const TEXT: &str = "Preamble text.
+
+# Book
+
+Intro words here.
+
+## Chapter *One*
+
+One two three.
+
+### Deep
+
+four
+
+## Chapter `Two`
+
+five six
+
+# Appendix
+
+seven";
+
+    #[test]
+    fn nests_sections_by_level() {
+        let result = report(TEXT);
+        let sections = result.sections();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].level, HeadingLevel::H1);
+        assert_eq!(sections[0].title, "Book");
+        assert_eq!(sections[0].subsections.len(), 2);
+        assert_eq!(sections[0].subsections[0].title, "Chapter One");
+        assert_eq!(sections[0].subsections[0].subsections[0].title, "Deep");
+        assert_eq!(sections[0].subsections[1].title, "Chapter Two");
+        assert_eq!(sections[1].title, "Appendix");
+    }
+
+    #[test]
+    fn counts_own_and_cumulative_words() {
+        let result = report(TEXT);
+        let book = &result.sections()[0];
+        assert_eq!(book.words, 4);
+        assert_eq!(book.subsections[0].words, 5);
+        assert_eq!(book.subsections[0].total(), 7);
+        assert_eq!(book.total(), 15);
+        assert_eq!(result.sections()[1].total(), 2);
+    }
+
+    #[test]
+    fn counts_preamble() {
+        let result = report(TEXT);
+        assert_eq!(result.preamble(), 2);
+        assert_eq!(
+            result.preamble() + result.sections().iter().map(Section::total).sum::<u64>(),
+            result.total()
+        );
+    }
+
+    #[test]
+    fn only_has_top_level_headings() {
+        let result = report("# Book\n\nIntro.\n\n> # Quoted heading\n\nMore book text.");
+        let sections = result.sections();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].title, "Book");
+        assert_eq!(sections[0].words, 5);
+    }
+
+    #[test]
+    fn excludes_heading_words_when_headings_are_excluded() {
+        let result = report_with_options("# Title\n\nBody text.", Options::empty());
+        assert_eq!(result.sections()[0].title, "Title");
+        assert_eq!(result.sections()[0].words, 2);
+    }
+}
@@ -0,0 +1,80 @@
+use std::ops::Range;
+
+/// How URLs are counted: autolinks like `<https://example.com>`, and bare URLs
+/// in the text. (The destinations of ordinary links are never counted.)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UrlMode {
+    /// URLs are not counted at all.
+    Exclude,
+    /// Each URL counts as a single word.
+    #[default]
+    Word,
+    /// Each of the words in a URL counts: `https`, `example`, `com`, and so on.
+    Segments,
+}
+
+/// The prefixes which start a bare URL in text.
+const PREFIXES: &[&str] = &["https://", "http://", "ftp://", "mailto:", "www."];
+
+/// Find the bare URLs in some text, like `https://example.com/page`.
+///
+/// A URL has to start at the beginning of a word, and runs until whitespace or
+/// a `<`. Trailing punctuation is left off, so that the period in "see
+/// https://example.com." is not part of the URL, and so is a closing `)` with
+/// no matching `(` in the URL, as in "(see https://example.com)".
+pub(crate) fn find(text: &str) -> Vec<Range<usize>> {
+    let lowercase = text.to_ascii_lowercase();
+    let mut urls = Vec::new();
+    let mut from = 0;
+
+    while let Some(start) = next_start(text, &lowercase, from) {
+        let len = text[start..]
+            .find(|c: char| c.is_whitespace() || c == '<')
+            .unwrap_or(text.len() - start);
+        let end = start + trim_end(&text[start..start + len]);
+        urls.push(start..end);
+        from = end;
+    }
+
+    urls
+}
+
+/// The start of the next URL at or after `from`, if there is one.
+fn next_start(text: &str, lowercase: &str, from: usize) -> Option<usize> {
+    PREFIXES
+        .iter()
+        .filter_map(|prefix| {
+            lowercase[from..]
+                .match_indices(prefix)
+                .map(|(index, _)| from + index)
+                .find(|&index| {
+                    let at_word_start = text[..index]
+                        .chars()
+                        .next_back()
+                        .is_none_or(|c| !c.is_alphanumeric());
+                    let has_rest = text[index + prefix.len()..]
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_alphanumeric());
+                    at_word_start && has_rest
+                })
+        })
+        .min()
+}
+
+/// The length of a URL without any trailing punctuation.
+fn trim_end(url: &str) -> usize {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"', '*', '_']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(inner) if inner.matches('(').count() <= inner.matches(')').count() => inner,
+            _ => trimmed,
+        };
+
+        if trimmed.len() == url.len() {
+            return url.len();
+        }
+        url = trimmed;
+    }
+}